@@ -0,0 +1,69 @@
+// Compares the zero-copy fast path of plain concatenation with the
+// line-based path on a generated file, run with `cargo bench`.
+// The size of the file in MiB can be set with CATR_BENCH_MB.
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 3;
+
+fn main() {
+    let mebibytes: usize = env::var("CATR_BENCH_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(256);
+    let input = tempfile::NamedTempFile::new().expect("cannot create the input file");
+    {
+        let mut writer = BufWriter::new(input.as_file());
+        let line = format!(
+            "{}\n",
+            "the quick brown fox jumps over the lazy dog ".repeat(2)
+        );
+        for _ in 0..mebibytes * 1024 * 1024 / line.len() {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+    let size = input.as_file().metadata().unwrap().len();
+    let path = input.path().to_str().unwrap();
+
+    // `--range 1:` selects every line, so the output is the same but
+    // goes through the line-based formatting
+    let fast = measure(&[path]);
+    let lines = measure(&["--range", "1:", path]);
+    report("zero-copy", size, fast);
+    report("line-based", size, lines);
+    println!(
+        "{:<12}{:>10.1}x",
+        "speedup",
+        lines.as_secs_f64() / fast.as_secs_f64()
+    );
+}
+
+// best wall-clock time of a few runs of catr writing to /dev/null
+fn measure(args: &[&str]) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let status = Command::new(env!("CARGO_BIN_EXE_catr"))
+                .args(args)
+                .stdout(Stdio::from(File::create("/dev/null").unwrap()))
+                .status()
+                .expect("cannot run catr");
+            assert!(status.success());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, size: u64, elapsed: Duration) {
+    println!(
+        "{:<12}{:>10.1} MiB/s ({:.3}s)",
+        name,
+        size as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64(),
+        elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,277 @@
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+
+/// Formats input the way `catr` does, writing to any `io::Write`.
+///
+/// ```
+/// let mut out = Vec::new();
+/// catr::Cat::new()
+///     .number(true)
+///     .show_ends(true)
+///     .write_to(&b"one\ntwo\n"[..], &mut out)
+///     .unwrap();
+/// assert_eq!(out, b"     1\tone$\n     2\ttwo$\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cat {
+    pub(crate) number: bool,
+    pub(crate) number_nonblank: bool,
+    pub(crate) squeeze_blank: bool,
+    pub(crate) show_ends: bool,
+    pub(crate) show_tabs: bool,
+    pub(crate) show_nonprinting: bool,
+    pub(crate) number_width: usize,
+    pub(crate) number_separator: String,
+    pub(crate) starting_line_number: i64,
+    pub(crate) ranges: Vec<RangeInclusive<usize>>,
+}
+
+impl Default for Cat {
+    fn default() -> Self {
+        Cat {
+            number: false,
+            number_nonblank: false,
+            squeeze_blank: false,
+            show_ends: false,
+            show_tabs: false,
+            show_nonprinting: false,
+            number_width: 6,
+            number_separator: "\t".to_string(),
+            starting_line_number: 1,
+            ranges: vec![],
+        }
+    }
+}
+
+/// Where the output stands after an input, so that line numbers, squeezing
+/// and lines left unterminated carry on into the next input like with GNU cat.
+#[derive(Debug, Clone)]
+pub struct LineState {
+    pub(crate) line_number: i64,
+    // the last line written did not end with a newline
+    pub(crate) in_line: bool,
+    pub(crate) prev_blank: bool,
+}
+
+impl LineState {
+    /// Start at line `line_number`, at the beginning of a line.
+    pub fn new(line_number: i64) -> Self {
+        LineState {
+            line_number,
+            in_line: false,
+            prev_blank: false,
+        }
+    }
+
+    /// The number the next numbered line gets.
+    pub fn line_number(&self) -> i64 {
+        self.line_number
+    }
+}
+
+impl Cat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number all output lines (`-n`).
+    pub fn number(mut self, yes: bool) -> Self {
+        self.number = yes;
+        self
+    }
+
+    /// Number non-empty output lines, overrides `number` (`-b`).
+    pub fn number_nonblank(mut self, yes: bool) -> Self {
+        self.number_nonblank = yes;
+        self
+    }
+
+    /// Suppress repeated empty output lines (`-s`).
+    pub fn squeeze_blank(mut self, yes: bool) -> Self {
+        self.squeeze_blank = yes;
+        self
+    }
+
+    /// Display `$` at the end of each line (`-E`).
+    pub fn show_ends(mut self, yes: bool) -> Self {
+        self.show_ends = yes;
+        self
+    }
+
+    /// Display TAB characters as `^I` (`-T`).
+    pub fn show_tabs(mut self, yes: bool) -> Self {
+        self.show_tabs = yes;
+        self
+    }
+
+    /// Use `^` and `M-` notation, except for LFD and TAB (`-v`).
+    pub fn show_nonprinting(mut self, yes: bool) -> Self {
+        self.show_nonprinting = yes;
+        self
+    }
+
+    /// Use `width` columns for line numbers.
+    pub fn number_width(mut self, width: usize) -> Self {
+        self.number_width = width;
+        self
+    }
+
+    /// Add `separator` after each line number.
+    pub fn number_separator(mut self, separator: &str) -> Self {
+        self.number_separator = separator.to_string();
+        self
+    }
+
+    /// First line number of the output.
+    pub fn starting_line_number(mut self, start: i64) -> Self {
+        self.starting_line_number = start;
+        self
+    }
+
+    /// Only print the lines in these (1-based, inclusive) ranges.
+    pub fn ranges(mut self, ranges: Vec<RangeInclusive<usize>>) -> Self {
+        self.ranges = ranges;
+        self
+    }
+
+    // true when the output is the input unchanged
+    pub(crate) fn is_plain(&self) -> bool {
+        !self.number
+            && !self.number_nonblank
+            && !self.squeeze_blank
+            && !self.show_ends
+            && !self.show_tabs
+            && !self.show_nonprinting
+            && self.ranges.is_empty()
+    }
+
+    /// Format `input` into `out`, numbering from the starting line number.
+    pub fn write_to<R: BufRead, W: Write>(&self, input: R, out: &mut W) -> io::Result<()> {
+        let mut state = LineState::new(self.starting_line_number);
+        self.write_lines(input, out, &mut state, false)
+    }
+
+    /// Format `input` into `out`, continuing from `state` and leaving it where
+    /// the output ends so that several inputs can be joined.
+    pub fn write_numbered<R: BufRead, W: Write>(
+        &self,
+        input: R,
+        out: &mut W,
+        state: &mut LineState,
+    ) -> io::Result<()> {
+        self.write_lines(input, out, state, true)
+    }
+
+    // reading stops after the last range unless `count_rest` asks for the
+    // lines after it to be counted, for the numbers of a following input
+    pub(crate) fn write_lines<R: BufRead, W: Write>(
+        &self,
+        mut input: R,
+        out: &mut W,
+        state: &mut LineState,
+        count_rest: bool,
+    ) -> io::Result<()> {
+        let mut line = Vec::new();
+        let mut line_num = 0;
+        let numbering = self.number || self.number_nonblank;
+        let last_line = match count_rest && numbering {
+            true => None,
+            false => self.ranges.iter().map(|range| *range.end()).max(),
+        };
+        loop {
+            if last_line.is_some_and(|last| line_num >= last) {
+                break;
+            }
+            line.clear();
+            if input.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            line_num += 1;
+            // an input can only finish the unterminated last line of the
+            // previous one, which was already numbered
+            let continued = line_num == 1 && state.in_line;
+            let blank = is_blank(&line) && !continued;
+            let numbered = !continued
+                && if self.number_nonblank {
+                    !blank
+                } else {
+                    self.number
+                };
+            if !self.ranges.is_empty() && !self.ranges.iter().any(|range| range.contains(&line_num))
+            {
+                // skipped lines still count so that -n and -b print the original numbers
+                if numbered {
+                    state.line_number += 1;
+                }
+                continue;
+            }
+            if blank && state.prev_blank && self.squeeze_blank {
+                continue;
+            }
+            state.prev_blank = blank;
+            if numbered {
+                write!(
+                    out,
+                    "{:>width$}{}",
+                    state.line_number,
+                    self.number_separator,
+                    width = self.number_width
+                )?;
+                state.line_number += 1;
+            }
+            state.in_line = !line.ends_with(b"\n");
+            self.write_line(&line, out)?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, line: &[u8], out: &mut impl Write) -> io::Result<()> {
+        if !self.show_ends && !self.show_tabs && !self.show_nonprinting {
+            return out.write_all(line);
+        }
+        let (mut content, newline) = match line.strip_suffix(b"\n") {
+            Some(content) => (content, true),
+            None => (line, false),
+        };
+        // like GNU cat, a carriage return ending the line is shown before the $
+        let mut ends = &b"$\n"[..];
+        if let Some(stripped) = content.strip_suffix(b"\r").filter(|_| self.show_ends) {
+            content = stripped;
+            ends = b"^M$\n";
+        }
+        for &byte in content {
+            match byte {
+                b'\t' if self.show_tabs => out.write_all(b"^I")?,
+                b'\t' => out.write_all(&[byte])?,
+                _ if self.show_nonprinting => write_nonprinting(byte, out)?,
+                _ => out.write_all(&[byte])?,
+            }
+        }
+        if newline {
+            out.write_all(if self.show_ends { ends } else { b"\n" })?;
+        }
+        Ok(())
+    }
+}
+
+// a line is blank when it holds nothing but its terminating newline
+fn is_blank(line: &[u8]) -> bool {
+    line.is_empty() || line == b"\n"
+}
+
+// render a byte with the ^ and M- notation used by GNU cat -v
+fn write_nonprinting(byte: u8, out: &mut impl Write) -> io::Result<()> {
+    let (meta, byte) = if byte >= 128 {
+        (true, byte - 128)
+    } else {
+        (false, byte)
+    };
+    if meta {
+        out.write_all(b"M-")?;
+    }
+    match byte {
+        0..=31 => out.write_all(&[b'^', byte + 64]),
+        127 => out.write_all(b"^?"),
+        _ => out.write_all(&[byte]),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{peek, MyResult};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{BufRead, BufReader};
+
+// number of bytes looked at when guessing the encoding of an input without a BOM
+const SAMPLE_SIZE: u64 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEncoding {
+    Auto,
+    Label(&'static Encoding),
+}
+
+pub fn parse_encoding(val: &str) -> Result<InputEncoding, String> {
+    if val.eq_ignore_ascii_case("auto") {
+        return Ok(InputEncoding::Auto);
+    }
+    Encoding::for_label(val.as_bytes())
+        .map(InputEncoding::Label)
+        .ok_or_else(|| format!("unknown encoding -- {}", val))
+}
+
+// transcode the input to UTF-8, a BOM always wins over the requested encoding
+pub fn decode(buffer: Box<dyn BufRead>, encoding: InputEncoding) -> MyResult<Box<dyn BufRead>> {
+    let (sample, buffer) = match encoding {
+        InputEncoding::Auto => peek(buffer, SAMPLE_SIZE)?,
+        InputEncoding::Label(_) => peek(buffer, 3)?,
+    };
+    let encoding = match (Encoding::for_bom(&sample), encoding) {
+        (Some((bom, _)), _) => bom,
+        (None, InputEncoding::Label(label)) => label,
+        (None, InputEncoding::Auto) => guess(&sample),
+    };
+    Ok(Box::new(BufReader::new(
+        DecodeReaderBytesBuilder::new()
+            .encoding(Some(encoding))
+            .bom_override(true)
+            .strip_bom(true)
+            .build(buffer),
+    )))
+}
+
+fn guess(sample: &[u8]) -> &'static Encoding {
+    if let Some(encoding) = utf16_without_bom(sample) {
+        return encoding;
+    }
+    match std::str::from_utf8(sample) {
+        // a sequence cut off by the end of the sample is still valid UTF-8
+        Err(err) if err.error_len().is_some() => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(sample, false);
+            detector.guess(None, true)
+        }
+        _ => UTF_8,
+    }
+}
+
+// text in UTF-16 is full of zero bytes on one side of each code unit
+fn utf16_without_bom(sample: &[u8]) -> Option<&'static Encoding> {
+    let units = sample.len() / 2;
+    if units == 0 {
+        return None;
+    }
+    let (mut even, mut odd) = (0, 0);
+    for pair in sample.chunks_exact(2) {
+        even += usize::from(pair[0] == 0);
+        odd += usize::from(pair[1] == 0);
+    }
+    match (even * 2 > units, odd * 2 > units) {
+        (true, false) => Some(UTF_16BE),
+        (false, true) => Some(UTF_16LE),
+        _ => None,
+    }
+}
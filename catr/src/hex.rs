@@ -0,0 +1,140 @@
+use std::io::{self, BufRead, Read, Write};
+
+#[derive(Debug, Clone)]
+pub struct HexFormat {
+    pub cols: usize,
+    pub group: usize,
+    pub upper: bool,
+    pub offset: u64,
+    pub length: Option<u64>,
+}
+
+impl Default for HexFormat {
+    fn default() -> Self {
+        HexFormat {
+            cols: 16,
+            group: 2,
+            upper: false,
+            offset: 0,
+            length: None,
+        }
+    }
+}
+
+// render the input in the layout of xxd: offset, grouped hex bytes and an ascii gutter
+pub fn dump(mut input: impl BufRead, format: &HexFormat, out: &mut impl Write) -> io::Result<()> {
+    io::copy(&mut input.by_ref().take(format.offset), &mut io::sink())?;
+    let mut input = input.take(format.length.unwrap_or(u64::MAX));
+    let mut offset = format.offset;
+    let mut row = vec![0; format.cols];
+    loop {
+        let bytes = fill_row(&mut input, &mut row)?;
+        if bytes == 0 {
+            break;
+        }
+        write_row(&row[..bytes], offset, format, out)?;
+        offset += bytes as u64;
+        if bytes < format.cols {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn fill_row(input: &mut impl Read, row: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < row.len() {
+        match input.read(&mut row[filled..]) {
+            Ok(0) => break,
+            Ok(bytes) => filled += bytes,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+fn write_row(row: &[u8], offset: u64, format: &HexFormat, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "{:08x}: ", offset)?;
+    for i in 0..format.cols {
+        match row.get(i) {
+            Some(byte) if format.upper => write!(out, "{:02X}", byte)?,
+            Some(byte) => write!(out, "{:02x}", byte)?,
+            None => out.write_all(b"  ")?,
+        }
+        if (i + 1) % format.group == 0 {
+            out.write_all(b" ")?;
+        }
+    }
+    if !format.cols.is_multiple_of(format.group) {
+        out.write_all(b" ")?;
+    }
+    out.write_all(b" ")?;
+    for &byte in row {
+        out.write_all(&[if (0x20..0x7f).contains(&byte) {
+            byte
+        } else {
+            b'.'
+        }])?;
+    }
+    out.write_all(b"\n")
+}
+
+// turn a dump produced by `dump` back into the original bytes, offsets are
+// relative to the first row and gaps between rows are filled with zeros
+pub fn reverse(mut input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+    let mut line = String::new();
+    let mut line_num = 0;
+    let mut base = None;
+    let mut position = 0;
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_num += 1;
+        let row = line.trim_end_matches(['\n', '\r']);
+        if row.is_empty() {
+            continue;
+        }
+        let (offset, bytes) = parse_row(row)
+            .ok_or_else(|| invalid(format!("invalid hex dump at line {}", line_num)))?;
+        let offset = offset
+            .checked_sub(*base.get_or_insert(offset))
+            .filter(|offset| *offset >= position)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "hex dump offsets go backwards at line {}",
+                    line_num
+                ))
+            })?;
+        io::copy(&mut io::repeat(0).take(offset - position), out)?;
+        out.write_all(&bytes)?;
+        position = offset + bytes.len() as u64;
+    }
+    Ok(())
+}
+
+fn parse_row(row: &str) -> Option<(u64, Vec<u8>)> {
+    let (offset, rest) = row.split_once(':')?;
+    let offset = u64::from_str_radix(offset.trim(), 16).ok()?;
+    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+    let hex: Vec<u8> = rest
+        .split("  ")
+        .next()?
+        .bytes()
+        .filter(|byte| *byte != b' ')
+        .collect();
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = hex
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some((offset, bytes))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,39 +1,194 @@
-use clap::{value_parser, Arg, ArgAction, Command};
+mod cat;
+mod encoding;
+mod hex;
+mod tac;
+mod terminal;
+
+pub use cat::{Cat, LineState};
+pub use encoding::InputEncoding;
+pub use hex::HexFormat;
+pub use tac::{Reverse, Tac};
+
+use clap::{builder::RangedU64ValueParser, value_parser, Arg, ArgAction, Command};
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Chain, Cursor, IsTerminal, Read, Write};
+use std::ops::RangeInclusive;
 
 pub type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    number_lines: bool,
-    number_nonblank_lines: bool,
-    squeeze_blank: bool,
+    cat: Cat,
+    number_per_file: bool,
+    decompress: bool,
+    hex: Option<HexFormat>,
+    hex_reverse: bool,
+    from_encoding: Option<InputEncoding>,
+    reverse: Option<Reverse>,
+    strict: bool,
+    highlight: bool,
+    language: Option<String>,
+    paging: bool,
 }
-struct ParsedFile {
-    buffer: Box<dyn BufRead>,
-    allow_blank: bool,
-    show_lines: bool,
-    squeeze_blank: bool,
+
+// remembers whether writing failed, so that an error can be blamed on
+// the output instead of the file being read
+struct Output<W> {
+    inner: W,
+    failed: bool,
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|_| self.failed = true)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|_| self.failed = true)
+    }
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}", filename, err),
-            Ok(buffer) => read_lines(ParsedFile {
-                buffer,
-                allow_blank: config.number_nonblank_lines,
-                show_lines: config.number_lines,
-                squeeze_blank: config.squeeze_blank,
-            }),
+    let stdout = io::stdout();
+    // highlighting and paging only ever apply to a terminal, so that
+    // pipelines always see the plain output
+    let terminal = stdout.is_terminal();
+    let highlighter = (config.highlight && terminal).then(terminal::Highlighter::new);
+    let mut pager = match config.paging && terminal {
+        true => terminal::pager().ok(),
+        false => None,
+    };
+    let inner: Box<dyn Write> = match pager.as_mut().and_then(|pager| pager.stdin.take()) {
+        Some(stdin) => Box::new(stdin),
+        None => Box::new(stdout.lock()),
+    };
+    let mut out = Output {
+        inner: BufWriter::new(inner),
+        failed: false,
+    };
+    // quitting the pager early closes our output, which is not an error
+    let pager_quit = |err: &io::Error| pager.is_some() && err.kind() == io::ErrorKind::BrokenPipe;
+    let plain = config.is_plain() && highlighter.is_none() && pager.is_none();
+    let mut state = LineState::new(config.cat.starting_line_number);
+    let mut failures = 0;
+    for (index, filename) in config.files.iter().enumerate() {
+        // the numbers only continue into the next input
+        let count_rest = !config.number_per_file && index + 1 < config.files.len();
+        if config.number_per_file {
+            state.line_number = config.cat.starting_line_number;
         }
+        if plain {
+            let err = match copy_plain(filename, &mut stdout.lock())? {
+                Ok(()) => continue,
+                Err(err) => err,
+            };
+            if config.strict {
+                return Err(err.into());
+            }
+            eprintln!("{}", err);
+            failures += 1;
+            continue;
+        }
+        let input = match &config.reverse {
+            Some(reverse) => tac::open(filename, reverse, config.decompress, config.from_encoding),
+            None => open(filename, config.decompress, config.from_encoding),
+        }
+        .and_then(|buffer| match &highlighter {
+            Some(highlighter) => {
+                highlighter.highlight(buffer, filename, config.language.as_deref())
+            }
+            None => Ok(buffer),
+        });
+        let err = match input {
+            Err(err) => format!("Failed to open {}: {}", filename, err),
+            Ok(buffer) => match cat_file(buffer, &config, &mut state, count_rest, &mut out) {
+                Err(err) if out.failed && pager_quit(&err) => break,
+                Err(err) if out.failed => return Err(err.into()),
+                Err(err) => format!("Failed to read {}: {}", filename, err),
+                Ok(()) => continue,
+            },
+        };
+        if config.strict {
+            out.flush()?;
+            return Err(err.into());
+        }
+        eprintln!("{}", err);
+        failures += 1;
+    }
+    match out.flush() {
+        Err(err) if !pager_quit(&err) => return Err(err.into()),
+        _ => drop(out),
+    }
+    if let Some(mut pager) = pager {
+        pager.wait()?;
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} inputs failed", failures, config.files.len()).into());
     }
     Ok(())
 }
 
+impl Config {
+    // true when the output is a plain concatenation of the inputs
+    fn is_plain(&self) -> bool {
+        self.cat.is_plain()
+            && self.hex.is_none()
+            && !self.hex_reverse
+            && !self.decompress
+            && self.from_encoding.is_none()
+            && self.reverse.is_none()
+    }
+}
+
+// with nothing to format, io::copy between a file or stdin and stdout lets the
+// kernel move the bytes (copy_file_range, sendfile or splice); a failed input is
+// reported in the inner result, while the outer one stops everything
+fn copy_plain(filename: &str, out: &mut io::StdoutLock) -> io::Result<Result<(), String>> {
+    let copied = match filename {
+        "-" => io::copy(&mut io::stdin().lock(), out),
+        _ => match File::open(filename) {
+            Ok(mut file) => io::copy(&mut file, out),
+            Err(err) => return Ok(Err(format!("Failed to open {}: {}", filename, err))),
+        },
+    };
+    match copied {
+        Ok(_) => Ok(Ok(())),
+        Err(err) if is_write_error(&err) => Err(err),
+        Err(err) => Ok(Err(format!("Failed to read {}: {}", filename, err))),
+    }
+}
+
+// the kernel copy has a single error for both ends and no `Output` to tell
+// them apart, these ones can only come from writing
+fn is_write_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::StorageFull
+            | io::ErrorKind::QuotaExceeded
+            | io::ErrorKind::FileTooLarge
+            | io::ErrorKind::WriteZero
+    )
+}
+
+fn cat_file(
+    buffer: impl BufRead,
+    config: &Config,
+    state: &mut LineState,
+    count_rest: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if config.hex_reverse {
+        return hex::reverse(buffer, out);
+    }
+    match &config.hex {
+        Some(format) => hex::dump(buffer, format, out),
+        None => config.cat.write_lines(buffer, out, state, count_rest),
+    }
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("catr")
         .version("0.1.0")
@@ -69,46 +224,545 @@ pub fn get_args() -> MyResult<Config> {
                 .conflicts_with("count_blanks")
                 .help("number all output lines"),
         )
+        .arg(
+            Arg::new("show_all")
+                .short('A')
+                .long("show-all")
+                .action(ArgAction::SetTrue)
+                .help("equivalent to -vET"),
+        )
+        .arg(
+            Arg::new("show_nonprinting_ends")
+                .short('e')
+                .action(ArgAction::SetTrue)
+                .help("equivalent to -vE"),
+        )
+        .arg(
+            Arg::new("show_ends")
+                .short('E')
+                .long("show-ends")
+                .action(ArgAction::SetTrue)
+                .help("display $ at end of each line"),
+        )
+        .arg(
+            Arg::new("show_nonprinting_tabs")
+                .short('t')
+                .action(ArgAction::SetTrue)
+                .help("equivalent to -vT"),
+        )
+        .arg(
+            Arg::new("show_tabs")
+                .short('T')
+                .long("show-tabs")
+                .action(ArgAction::SetTrue)
+                .help("display TAB characters as ^I"),
+        )
+        .arg(
+            Arg::new("show_nonprinting")
+                .short('v')
+                .long("show-nonprinting")
+                .action(ArgAction::SetTrue)
+                .help("use ^ and M- notation, except for LFD and TAB"),
+        )
+        .arg(
+            Arg::new("number_per_file")
+                .long("number-per-file")
+                .action(ArgAction::SetTrue)
+                .help("restart line numbers for each input file"),
+        )
+        .arg(
+            Arg::new("number_width")
+                .long("number-width")
+                .value_name("NUMBER")
+                .default_value("6")
+                .value_parser(value_parser!(usize))
+                .help("use NUMBER columns for line numbers"),
+        )
+        .arg(
+            Arg::new("number_separator")
+                .long("number-separator")
+                .value_name("STRING")
+                .default_value("\t")
+                .allow_hyphen_values(true)
+                .value_parser(value_parser!(String))
+                .help("add STRING after (possible) line number"),
+        )
+        .arg(
+            Arg::new("starting_line_number")
+                .long("starting-line-number")
+                .value_name("NUMBER")
+                .default_value("1")
+                .allow_negative_numbers(true)
+                .value_parser(value_parser!(i64))
+                .help("first line number of the output"),
+        )
+        .arg(
+            Arg::new("decompress")
+                .short('z')
+                .long("decompress")
+                .action(ArgAction::SetTrue)
+                .help("decompress gzip, bzip2, xz and zstd inputs"),
+        )
+        .arg(
+            Arg::new("hex")
+                .long("hex")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "count_blanks",
+                    "show_line_number",
+                    "squeeze-blank",
+                    "show_all",
+                    "show_nonprinting_ends",
+                    "show_ends",
+                    "show_nonprinting_tabs",
+                    "show_tabs",
+                    "show_nonprinting",
+                ])
+                .help("display a hex dump of the input like xxd"),
+        )
+        .arg(
+            Arg::new("hex_cols")
+                .long("hex-cols")
+                .value_name("NUMBER")
+                .default_value("16")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .help("number of bytes per hex dump line"),
+        )
+        .arg(
+            Arg::new("hex_group")
+                .long("hex-group")
+                .value_name("NUMBER")
+                .default_value("2")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .help("number of bytes per hex dump group"),
+        )
+        .arg(
+            Arg::new("hex_upper")
+                .long("hex-upper")
+                .action(ArgAction::SetTrue)
+                .help("use upper case hex digits"),
+        )
+        .arg(
+            Arg::new("hex_offset")
+                .long("hex-offset")
+                .value_name("BYTES")
+                .default_value("0")
+                .value_parser(value_parser!(u64))
+                .help("start the hex dump at this byte offset"),
+        )
+        .arg(
+            Arg::new("hex_length")
+                .long("hex-length")
+                .value_name("BYTES")
+                .value_parser(value_parser!(u64))
+                .help("stop the hex dump after this many bytes"),
+        )
+        .arg(
+            Arg::new("hex_reverse")
+                .long("hex-reverse")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("hex")
+                .help("convert a hex dump back into binary"),
+        )
+        .arg(
+            Arg::new("range")
+                .long("range")
+                .value_name("START:END")
+                .conflicts_with_all(["hex", "hex_reverse"])
+                .value_parser(parse_ranges)
+                .help("only print lines START to END, several ranges may be separated by commas"),
+        )
+        .arg(
+            Arg::new("from_encoding")
+                .long("from-encoding")
+                .value_name("ENCODING")
+                .value_parser(encoding::parse_encoding)
+                .help("convert input from ENCODING (or detect it with `auto`) to UTF-8"),
+        )
+        .arg(
+            Arg::new("reverse")
+                .long("reverse")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("hex_reverse")
+                .help("print the lines of each file last-to-first like tac"),
+        )
+        .arg(
+            Arg::new("separator")
+                .long("separator")
+                .value_name("STRING")
+                .default_value("\n")
+                .allow_hyphen_values(true)
+                .value_parser(value_parser!(String))
+                .help("use STRING as the record separator of --reverse"),
+        )
+        .arg(
+            Arg::new("regex")
+                .long("regex")
+                .action(ArgAction::SetTrue)
+                .requires("reverse")
+                .help("interpret the separator as a regular expression"),
+        )
+        .arg(
+            Arg::new("before")
+                .long("before")
+                .action(ArgAction::SetTrue)
+                .requires("reverse")
+                .help("attach the separator before instead of after each record"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .help("stop at the first file that cannot be opened or read"),
+        )
+        .arg(
+            Arg::new("highlight")
+                .long("highlight")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "hex",
+                    "hex_reverse",
+                    "show_all",
+                    "show_nonprinting_ends",
+                    "show_ends",
+                    "show_nonprinting_tabs",
+                    "show_tabs",
+                    "show_nonprinting",
+                ])
+                .help("highlight syntax by file extension when writing to a terminal"),
+        )
+        .arg(
+            Arg::new("language")
+                .long("language")
+                .value_name("LANGUAGE")
+                .requires("highlight")
+                .value_parser(value_parser!(String))
+                .help("highlight the input as LANGUAGE instead of guessing it"),
+        )
+        .arg(
+            Arg::new("paging")
+                .long("paging")
+                .action(ArgAction::SetTrue)
+                .help("page the output through $PAGER (or less) when writing to a terminal"),
+        )
         .get_matches();
+    let reverse = match matches.get_flag("reverse") {
+        true => Some(Reverse::new(
+            matches
+                .get_one::<String>("separator")
+                .expect("separator should have a default"),
+            matches.get_flag("regex"),
+            matches.get_flag("before"),
+        )?),
+        false => None,
+    };
+    let show_all = matches.get_flag("show_all");
+    let show_nonprinting_ends = matches.get_flag("show_nonprinting_ends");
+    let show_nonprinting_tabs = matches.get_flag("show_nonprinting_tabs");
     Ok(Config {
         files: matches
             .get_many::<String>("files")
             .expect("files should be specified")
-            .into_iter()
             .map(|s| s.as_str().to_string())
             .collect(),
-        number_lines: matches.get_flag("show_line_number"),
-        number_nonblank_lines: matches.get_flag("count_blanks"),
-        squeeze_blank: matches.get_flag("squeeze-blank"),
+        cat: Cat::new()
+            .number(matches.get_flag("show_line_number"))
+            .number_nonblank(!matches.get_flag("count_blanks"))
+            .squeeze_blank(matches.get_flag("squeeze-blank"))
+            .show_ends(show_all || show_nonprinting_ends || matches.get_flag("show_ends"))
+            .show_tabs(show_all || show_nonprinting_tabs || matches.get_flag("show_tabs"))
+            .show_nonprinting(
+                show_all
+                    || show_nonprinting_ends
+                    || show_nonprinting_tabs
+                    || matches.get_flag("show_nonprinting"),
+            )
+            .number_width(
+                *matches
+                    .get_one::<usize>("number_width")
+                    .expect("number width should have a default"),
+            )
+            .number_separator(
+                matches
+                    .get_one::<String>("number_separator")
+                    .expect("number separator should have a default"),
+            )
+            .starting_line_number(
+                *matches
+                    .get_one::<i64>("starting_line_number")
+                    .expect("starting line number should have a default"),
+            )
+            .ranges(
+                matches
+                    .get_one::<Vec<RangeInclusive<usize>>>("range")
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+        number_per_file: matches.get_flag("number_per_file"),
+        decompress: matches.get_flag("decompress"),
+        hex: matches.get_flag("hex").then(|| HexFormat {
+            cols: *matches
+                .get_one::<usize>("hex_cols")
+                .expect("hex cols should have a default"),
+            group: *matches
+                .get_one::<usize>("hex_group")
+                .expect("hex group should have a default"),
+            upper: matches.get_flag("hex_upper"),
+            offset: *matches
+                .get_one::<u64>("hex_offset")
+                .expect("hex offset should have a default"),
+            length: matches.get_one::<u64>("hex_length").copied(),
+        }),
+        hex_reverse: matches.get_flag("hex_reverse"),
+        from_encoding: matches.get_one::<InputEncoding>("from_encoding").copied(),
+        reverse,
+        strict: matches.get_flag("strict"),
+        highlight: matches.get_flag("highlight"),
+        language: matches.get_one::<String>("language").cloned(),
+        paging: matches.get_flag("paging"),
     })
 }
 
-pub fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+// parse line ranges like `200:260`, `:50` or `100:` separated by commas,
+// a single number selects one line
+pub fn parse_ranges(val: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let parse_line = |num: &str, default: usize| match num {
+        "" => Ok(default),
+        _ => match num.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("illegal line number -- {}", num)),
+        },
+    };
+    val.split(',')
+        .map(|range| {
+            let (start, end) = match range.split_once(':') {
+                Some((start, end)) => (parse_line(start, 1)?, parse_line(end, usize::MAX)?),
+                None if !range.is_empty() => {
+                    let line = parse_line(range, 0)?;
+                    (line, line)
+                }
+                None => return Err(format!("illegal range -- {}", val)),
+            };
+            if start > end {
+                return Err(format!(
+                    "range start ({}) must not be greater than its end ({})",
+                    start, end
+                ));
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+pub fn open(
+    filename: &str,
+    decompress: bool,
+    from_encoding: Option<InputEncoding>,
+) -> MyResult<Box<dyn BufRead>> {
+    let mut buffer: Box<dyn BufRead> = match filename {
+        "-" => Box::new(BufReader::new(io::stdin())),
+        _ => Box::new(BufReader::new(File::open(filename)?)),
+    };
+    if decompress {
+        buffer = decoder(buffer)?;
+    }
+    match from_encoding {
+        Some(encoding) => encoding::decode(buffer, encoding),
+        None => Ok(buffer),
     }
 }
-fn read_lines(file: ParsedFile) {
-    let mut i = 1;
-    let mut prev_blank = 10;
-    for (line_num, line) in file.buffer.lines().map(|x| x.unwrap()).enumerate() {
-        if prev_blank + 1 == line_num && line.trim().is_empty() && file.squeeze_blank {
-            prev_blank = line_num;
-            continue;
-        }
-        if line.trim().is_empty() {
-            prev_blank = line_num;
-        }
-        if !file.show_lines && file.allow_blank {
-            println!("{}", line);
-            continue;
+
+type Peeked = BufReader<Chain<Cursor<Vec<u8>>, Box<dyn BufRead>>>;
+
+// read up to `len` bytes from the start of the input and hand back
+// a reader that still yields them
+pub(crate) fn peek(mut buffer: Box<dyn BufRead>, len: u64) -> io::Result<(Vec<u8>, Peeked)> {
+    let mut head = Vec::new();
+    buffer.by_ref().take(len).read_to_end(&mut head)?;
+    let buffer = BufReader::new(Cursor::new(head.clone()).chain(buffer));
+    Ok((head, buffer))
+}
+
+// sniff the magic bytes of the input and wrap it in the matching decoder,
+// inputs in an unknown format are passed through unchanged
+fn decoder(buffer: Box<dyn BufRead>) -> MyResult<Box<dyn BufRead>> {
+    let (magic, buffer) = peek(buffer, 6)?;
+    Ok(match magic.as_slice() {
+        [0x1f, 0x8b, ..] => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(buffer))),
+        [b'B', b'Z', b'h', ..] => {
+            Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(buffer)))
         }
-        if !file.allow_blank && line.trim().is_empty() {
-            println!("{}", line);
-        } else {
-            println!("{:>6}\t{}", i, line);
-            i += 1;
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00] => Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(buffer),
+        )),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(buffer)?,
+        )),
+        _ => Box::new(buffer),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ranges, terminal::Highlighter, Cat, LineState, Reverse, Tac};
+    use std::io::{BufRead, Cursor, Read};
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges("200:260"), Ok(vec![200..=260]));
+        assert_eq!(parse_ranges(":50"), Ok(vec![1..=50]));
+        assert_eq!(parse_ranges("100:"), Ok(vec![100..=usize::MAX]));
+        assert_eq!(parse_ranges("3"), Ok(vec![3..=3]));
+        assert_eq!(
+            parse_ranges("1:2,5,7:"),
+            Ok(vec![1..=2, 5..=5, 7..=usize::MAX])
+        );
+        assert!(parse_ranges("").is_err());
+        assert!(parse_ranges("0:3").is_err());
+        assert!(parse_ranges("a:3").is_err());
+        assert!(parse_ranges("1:3,").is_err());
+        assert_eq!(
+            parse_ranges("5:2"),
+            Err("range start (5) must not be greater than its end (2)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tac_across_blocks() {
+        let lines: Vec<String> = (0..5000)
+            .map(|i| format!("{}{}", "-".repeat(i % 37), i))
+            .collect();
+        let input = lines.join("\n");
+        let mut output = String::new();
+        Tac::new(
+            Cursor::new(input),
+            Reverse::new("\n", false, false).unwrap(),
+        )
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+        let mut expected: Vec<String> = lines.iter().map(|line| format!("{}\n", line)).collect();
+        expected.last_mut().unwrap().pop();
+        expected.reverse();
+        assert_eq!(output, expected.concat());
+    }
+
+    #[test]
+    fn test_tac_long_records() {
+        // records longer than a block, each ending in a separator that may be
+        // split between two reads
+        let texts: Vec<String> = [20000, 8191, 3, 50000, 8190]
+            .iter()
+            .map(|&len| "x".repeat(len))
+            .collect();
+        let input: String = texts.iter().map(|text| format!("{}<>", text)).collect();
+        let reversed: String = texts
+            .iter()
+            .rev()
+            .map(|text| format!("{}<>", text))
+            .collect();
+        let starting: String = texts[1..]
+            .iter()
+            .rev()
+            .map(|text| format!("<>{}", text))
+            .collect();
+        for (before, expected) in [
+            (false, reversed),
+            // the trailing separator is a record of its own, the first text
+            // has none in front of it
+            (true, format!("<>{}{}", starting, texts[0])),
+        ] {
+            let mut output = String::new();
+            Tac::new(
+                Cursor::new(input.clone()),
+                Reverse::new("<>", false, before).unwrap(),
+            )
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+            assert!(output == expected, "before: {}", before);
         }
     }
+
+    #[test]
+    fn test_cat_write_to() {
+        let input = &b"a\tb\n\n\n\n\x7fc\nd"[..];
+        let mut out = Vec::new();
+        Cat::new().write_to(input, &mut out).unwrap();
+        assert_eq!(out, input);
+
+        out.clear();
+        Cat::new()
+            .number_nonblank(true)
+            .squeeze_blank(true)
+            .show_tabs(true)
+            .show_nonprinting(true)
+            .write_to(input, &mut out)
+            .unwrap();
+        assert_eq!(out, b"     1\ta^Ib\n\n     2\t^?c\n     3\td");
+    }
+
+    #[test]
+    fn test_cat_write_numbered() {
+        let cat = Cat::new()
+            .number(true)
+            .number_width(2)
+            .number_separator(": ")
+            .ranges(vec![2..=2]);
+        let mut state = LineState::new(10);
+        let mut out = Vec::new();
+        cat.write_numbered(&b"one\ntwo\nthree\n"[..], &mut out, &mut state)
+            .unwrap();
+        cat.write_numbered(&b"four\nfive\n"[..], &mut out, &mut state)
+            .unwrap();
+        assert_eq!(out, b"11: two\n14: five\n");
+        assert_eq!(state.line_number(), 15);
+    }
+
+    #[test]
+    fn test_cat_write_numbered_across_inputs() {
+        let cat = Cat::new().number(true).squeeze_blank(true);
+        let mut state = LineState::new(1);
+        let mut out = Vec::new();
+        // the second input finishes the line left open by the first one, and
+        // the blank lines meeting at the boundary are squeezed together
+        cat.write_numbered(&b"one\ntw"[..], &mut out, &mut state)
+            .unwrap();
+        cat.write_numbered(&b"o\n\n"[..], &mut out, &mut state)
+            .unwrap();
+        cat.write_numbered(&b"\nthree\n"[..], &mut out, &mut state)
+            .unwrap();
+        assert_eq!(out, b"     1\tone\n     2\ttwo\n     3\t\n     4\tthree\n");
+        assert_eq!(state.line_number(), 5);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let source = "fn main() {\n\n    println!(\"hi\");\n}\n";
+        let highlighter = Highlighter::new();
+        let mut output = String::new();
+        highlighter
+            .highlight(Box::new(source.as_bytes()), "main.rs", None)
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert!(output.contains("\x1b["));
+        // blank lines are left alone and every other line ends in a reset
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert_eq!(lines[1], "");
+        assert!(lines[0].ends_with("\x1b[0m"));
+        let plain = regex::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&output, "");
+        assert_eq!(plain, source);
+
+        // no known syntax means no escapes at all
+        let mut plain = Vec::new();
+        highlighter
+            .highlight(Box::new(source.as_bytes()), "notes", None)
+            .unwrap()
+            .read_until(0, &mut plain)
+            .unwrap();
+        assert_eq!(plain, source.as_bytes());
+    }
 }
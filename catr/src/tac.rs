@@ -0,0 +1,162 @@
+use crate::{InputEncoding, MyResult};
+use regex::bytes::Regex;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+const BLOCK_SIZE: usize = 8192;
+
+#[derive(Debug, Clone)]
+pub struct Reverse {
+    pub separator: Regex,
+    pub before: bool,
+    // the longest match of the separator, when it has one and the match does
+    // not depend on what surrounds it
+    pub(crate) max_len: Option<usize>,
+}
+
+impl Reverse {
+    pub fn new(separator: &str, regex: bool, before: bool) -> MyResult<Self> {
+        if separator.is_empty() {
+            return Err("separator cannot be empty".into());
+        }
+        let pattern = if regex {
+            separator.to_string()
+        } else {
+            regex::escape(separator)
+        };
+        let separator = Regex::new(&pattern)?;
+        let max_len = regex_syntax::Parser::new()
+            .parse(&pattern)
+            .ok()
+            .map(|hir| hir.properties().clone())
+            .filter(|properties| properties.look_set().is_empty())
+            .and_then(|properties| properties.maximum_len());
+        Ok(Reverse {
+            separator,
+            before,
+            max_len,
+        })
+    }
+}
+
+// regular files are read backwards in place, anything else (stdin, pipes,
+// decoded streams) is spooled to a temporary file first
+pub fn open(
+    filename: &str,
+    reverse: &Reverse,
+    decompress: bool,
+    from_encoding: Option<InputEncoding>,
+) -> MyResult<Box<dyn BufRead>> {
+    let seekable = filename != "-"
+        && !decompress
+        && from_encoding.is_none()
+        && fs::metadata(filename)?.is_file();
+    let file = if seekable {
+        File::open(filename)?
+    } else {
+        let mut spool = tempfile::tempfile()?;
+        io::copy(
+            &mut crate::open(filename, decompress, from_encoding)?,
+            &mut spool,
+        )?;
+        spool
+    };
+    Ok(Box::new(BufReader::new(Tac::new(file, reverse.clone())?)))
+}
+
+// yields the records of a seekable input last-to-first, reading it from the end
+pub struct Tac<F> {
+    file: F,
+    reverse: Reverse,
+    // offset in the file where `pending` starts
+    pos: u64,
+    // bytes between `pos` and the last record handed out
+    pending: Vec<u8>,
+    ready: Vec<u8>,
+    consumed: usize,
+    block: usize,
+}
+
+impl<F: Read + Seek> Tac<F> {
+    pub fn new(mut file: F, reverse: Reverse) -> io::Result<Self> {
+        let pos = file.seek(SeekFrom::End(0))?;
+        Ok(Tac {
+            file,
+            reverse,
+            pos,
+            pending: vec![],
+            ready: vec![],
+            consumed: 0,
+            block: BLOCK_SIZE,
+        })
+    }
+
+    fn step(&mut self) -> io::Result<()> {
+        let mut len = 0;
+        if self.pos > 0 {
+            len = self.pos.min(self.block as u64) as usize;
+            self.pos -= len as u64;
+            self.file.seek(SeekFrom::Start(self.pos))?;
+            let mut chunk = vec![0; len];
+            self.file.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&self.pending);
+            self.pending = chunk;
+        }
+        let at_start = self.pos == 0;
+        // what was pending had already been searched, a new match starts in
+        // the chunk just read and reaches at most `max_len` into the rest
+        let searched = match self.reverse.max_len {
+            Some(max_len) => (len + max_len).min(self.pending.len()),
+            None => self.pending.len(),
+        };
+        // a match touching the start of the buffer may continue into the unread part
+        let bounds: Vec<usize> = self
+            .reverse
+            .separator
+            .find_iter(&self.pending[..searched])
+            .filter(|m| m.start() > 0 || at_start)
+            .map(|m| {
+                if self.reverse.before {
+                    m.start()
+                } else {
+                    m.end()
+                }
+            })
+            .collect();
+        let mut end = self.pending.len();
+        let mut emitted = false;
+        for &bound in bounds.iter().rev() {
+            if bound < end {
+                self.ready.extend_from_slice(&self.pending[bound..end]);
+                end = bound;
+                emitted = true;
+            }
+        }
+        if at_start {
+            self.ready.extend_from_slice(&self.pending[..end]);
+            end = 0;
+        }
+        self.pending.truncate(end);
+        // grow the reads while looking for the start of a very long record, the
+        // separator ending it does not count
+        self.block = if emitted { BLOCK_SIZE } else { self.block * 2 };
+        Ok(())
+    }
+}
+
+impl<F: Read + Seek> Read for Tac<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.consumed == self.ready.len() {
+            if self.pos == 0 && self.pending.is_empty() {
+                return Ok(0);
+            }
+            self.ready.clear();
+            self.consumed = 0;
+            self.step()?;
+        }
+        let bytes = buf.len().min(self.ready.len() - self.consumed);
+        buf[..bytes].copy_from_slice(&self.ready[self.consumed..self.consumed + bytes]);
+        self.consumed += bytes;
+        Ok(bytes)
+    }
+}
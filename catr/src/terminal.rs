@@ -0,0 +1,142 @@
+use crate::MyResult;
+use std::env;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+const THEME: &str = "base16-ocean.dark";
+const RESET: &str = "\x1b[0m";
+
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme: ThemeSet::load_defaults()
+                .themes
+                .remove(THEME)
+                .expect("default themes should include the highlighting theme"),
+        }
+    }
+
+    // wrap the input so that it yields its lines with terminal colour escapes,
+    // inputs without a known syntax are passed through unchanged
+    pub fn highlight<'a>(
+        &'a self,
+        buffer: Box<dyn BufRead + 'a>,
+        filename: &str,
+        language: Option<&str>,
+    ) -> MyResult<Box<dyn BufRead + 'a>> {
+        let syntax = match language {
+            Some(language) => Some(
+                self.syntaxes
+                    .find_syntax_by_token(language)
+                    .ok_or_else(|| format!("unknown language -- {}", language))?,
+            ),
+            None => self.syntax_for_file(filename),
+        };
+        Ok(match syntax {
+            Some(syntax) => Box::new(BufReader::new(Highlighted {
+                buffer,
+                lines: HighlightLines::new(syntax, &self.theme),
+                syntaxes: &self.syntaxes,
+                line: vec![],
+                ready: vec![],
+                consumed: 0,
+            })),
+            None => buffer,
+        })
+    }
+
+    fn syntax_for_file(&self, filename: &str) -> Option<&SyntaxReference> {
+        let path = Path::new(filename);
+        path.extension()
+            .or(path.file_name())
+            .and_then(|token| token.to_str())
+            .and_then(|token| self.syntaxes.find_syntax_by_extension(token))
+    }
+}
+
+struct Highlighted<'a> {
+    buffer: Box<dyn BufRead + 'a>,
+    lines: HighlightLines<'a>,
+    syntaxes: &'a SyntaxSet,
+    line: Vec<u8>,
+    ready: Vec<u8>,
+    consumed: usize,
+}
+
+impl Highlighted<'_> {
+    fn next_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        self.ready.clear();
+        self.consumed = 0;
+        if self.buffer.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(false);
+        }
+        let text = match std::str::from_utf8(&self.line) {
+            Ok(text) => text,
+            Err(_) => {
+                self.ready.extend_from_slice(&self.line);
+                return Ok(true);
+            }
+        };
+        let regions = self
+            .lines
+            .highlight_line(text, self.syntaxes)
+            .map_err(io::Error::other)?;
+        let (content, newline) = match text.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (text, ""),
+        };
+        // empty lines stay empty so that -b and -s still recognise them
+        if !content.is_empty() {
+            let escaped = as_24_bit_terminal_escaped(&regions, false);
+            self.ready
+                .extend_from_slice(escaped.trim_end_matches('\n').as_bytes());
+            self.ready.extend_from_slice(RESET.as_bytes());
+        }
+        self.ready.extend_from_slice(newline.as_bytes());
+        Ok(true)
+    }
+}
+
+impl Read for Highlighted<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.ready.len() && !self.next_line()? {
+            return Ok(0);
+        }
+        let bytes = buf.len().min(self.ready.len() - self.consumed);
+        buf[..bytes].copy_from_slice(&self.ready[self.consumed..self.consumed + bytes]);
+        self.consumed += bytes;
+        Ok(bytes)
+    }
+}
+
+// start $PAGER (or less) reading from a pipe, less is told to keep colours
+// and to quit right away when the output fits on the screen
+pub fn pager() -> io::Result<Child> {
+    let pager = env::var("PAGER").unwrap_or_default();
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    let mut args: Vec<&str> = words.collect();
+    if args.is_empty()
+        && Path::new(program)
+            .file_name()
+            .is_some_and(|name| name == "less")
+    {
+        args = vec!["-R", "-F", "-X"];
+    }
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+}
@@ -1,4 +1,4 @@
-use assert_cmd::Command;
+use assert_cmd::{assert::OutputAssertExt, Command};
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::fs;
@@ -10,6 +10,9 @@ const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
 const JET: &str = "tests/inputs/jet.txt";
+const MIXED: &str = "tests/inputs/mixed.bin";
+const BLANK_END: &str = "tests/inputs/blank-end.txt";
+const BLANK_START: &str = "tests/inputs/blank-start.txt";
 
 // --------------------------------------------------
 #[test]
@@ -46,11 +49,80 @@ fn skips_bad_file() -> MyResult<()> {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn continues_after_bad_file() -> MyResult<()> {
+    let bad = gen_bad_file();
+    let expected = format!(
+        "{}{}",
+        fs::read_to_string(FOX)?,
+        fs::read_to_string(SPIDERS)?
+    );
+    Command::cargo_bin(PRG)?
+        .args([FOX, &bad, SPIDERS])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::is_match(format!(
+            "Failed to open {bad}: .* [(]os error 2[)]\n1 of 3 inputs failed"
+        ))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_read_error() -> MyResult<()> {
+    Command::cargo_bin(PRG)?
+        .args([FOX, "tests/inputs", SPIDERS])
+        .assert()
+        .failure()
+        .stdout(format!(
+            "{}{}",
+            fs::read_to_string(FOX)?,
+            fs::read_to_string(SPIDERS)?
+        ))
+        .stderr(predicate::str::contains(
+            "Failed to read tests/inputs: Is a directory",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn strict_stops_at_first_error() -> MyResult<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--strict", FOX, &bad, SPIDERS])
+        .assert()
+        .failure()
+        .stdout(fs::read_to_string(FOX)?)
+        .stderr(predicate::str::is_match(format!(
+            "^Failed to open {bad}: .* [(]os error 2[)]\n$"
+        ))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_error_is_not_an_input_failure() -> MyResult<()> {
+    // plain concatenation and formatted output fail the same way
+    for args in [&[FOX, SPIDERS][..], &["-n", FOX, SPIDERS]] {
+        std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(args)
+            .stdout(fs::OpenOptions::new().write(true).open("/dev/full")?)
+            .output()?
+            .assert()
+            .failure()
+            .stderr("No space left on device (os error 28)\n");
+    }
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> MyResult<()> {
     let expected = fs::read_to_string(expected_file)?;
@@ -63,6 +135,15 @@ fn run(args: &[&str], expected_file: &str) -> MyResult<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+fn run_bytes(args: &[&str], expected_file: &str) -> MyResult<()> {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run_stdin(
     input_file: &str,
@@ -202,6 +283,36 @@ fn all_b() -> MyResult<()> {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn mixed_fox_n_joins_unterminated_line() -> MyResult<()> {
+    run_bytes(&["-n", MIXED, FOX], "tests/expected/mixed.bin-fox.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_fox_b_joins_unterminated_line() -> MyResult<()> {
+    run_bytes(&["-b", MIXED, FOX], "tests/expected/mixed.bin-fox.b.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blank_s_across_files() -> MyResult<()> {
+    run(
+        &["-s", BLANK_END, BLANK_START],
+        "tests/expected/blank-end-start.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn blank_sn_across_files() -> MyResult<()> {
+    run(
+        &["-sn", BLANK_END, BLANK_START],
+        "tests/expected/blank-end-start.sn.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn jet_s() -> MyResult<()> {
@@ -250,3 +361,462 @@ fn jet_stdin_sn() -> MyResult<()> {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn mixed() -> MyResult<()> {
+    run_bytes(&[MIXED], MIXED)
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_stdin() -> MyResult<()> {
+    let input = fs::read(MIXED)?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, input);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_n() -> MyResult<()> {
+    run_bytes(&["-n", MIXED], "tests/expected/mixed.bin.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_show_all() -> MyResult<()> {
+    for flag in &["-A", "--show-all", "-vET"] {
+        run_bytes(&[flag, MIXED], "tests/expected/mixed.bin.A.out")?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_show_ends_crlf() -> MyResult<()> {
+    for flag in &["-E", "--show-ends"] {
+        run_bytes(&[flag, MIXED], "tests/expected/mixed.bin.E.out")?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_e() -> MyResult<()> {
+    run_bytes(&["-e", MIXED], "tests/expected/mixed.bin.e.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_t() -> MyResult<()> {
+    run_bytes(&["-t", MIXED], "tests/expected/mixed.bin.t.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_n_show_tabs() -> MyResult<()> {
+    run_bytes(
+        &["-n", "--show-tabs", MIXED],
+        "tests/expected/mixed.bin.n.T.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_show_nonprinting() -> MyResult<()> {
+    run(
+        &["--show-nonprinting", BUSTLE],
+        "tests/expected/the-bustle.txt.v.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn jet_sbe() -> MyResult<()> {
+    run(&[JET, "-s", "-b", "-E"], "tests/expected/jet.txt.s.b.E.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_per_file() -> MyResult<()> {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-n", "--number-per-file"],
+        "tests/expected/all.n.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_b_per_file() -> MyResult<()> {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-b", "--number-per-file"],
+        "tests/expected/all.b.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_n_format() -> MyResult<()> {
+    run(
+        &[
+            "-n",
+            "--number-width",
+            "3",
+            "--number-separator",
+            ": ",
+            "--starting-line-number",
+            "10",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.n.w3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_decompress() -> MyResult<()> {
+    for ext in &["gz", "bz2", "xz", "zst"] {
+        let compressed = format!("{BUSTLE}.{ext}");
+        run(&["-z", &compressed], "tests/expected/the-bustle.txt.out")?;
+        run(
+            &["--decompress", "-n", &compressed],
+            "tests/expected/the-bustle.txt.n.out",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_stdin_decompress() -> MyResult<()> {
+    let input = fs::read(format!("{BUSTLE}.zst"))?;
+    let expected = fs::read_to_string("tests/expected/the-bustle.txt.b.out")?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["-z", "-b"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_member_decompress() -> MyResult<()> {
+    let expected = format!(
+        "{}{}",
+        fs::read_to_string(FOX)?,
+        fs::read_to_string(SPIDERS)?
+    );
+    let output = Command::cargo_bin(PRG)?
+        .args(["-z", "tests/inputs/fox-spiders.txt.gz"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_passes_plain_input() -> MyResult<()> {
+    run_bytes(&["-z", MIXED], MIXED)?;
+    run_bytes(&["-z", EMPTY], EMPTY)
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_hex() -> MyResult<()> {
+    run(&["--hex", BUSTLE], "tests/expected/the-bustle.txt.hex.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_hex() -> MyResult<()> {
+    run(&["--hex", MIXED], "tests/expected/mixed.bin.hex.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_hex_layout() -> MyResult<()> {
+    run(
+        &["--hex", "--hex-cols", "10", "--hex-group", "3", FOX],
+        "tests/expected/fox.txt.hex.c10.g3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_hex_range() -> MyResult<()> {
+    run(
+        &[
+            "--hex",
+            "--hex-upper",
+            "--hex-offset",
+            "26",
+            "--hex-length",
+            "40",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.hex.u.s26.l40.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_round_trip() -> MyResult<()> {
+    for entry in fs::read_dir("tests/inputs")? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        let input = fs::read(&path)?;
+        let dump = Command::cargo_bin(PRG)?
+            .args(["--hex", "--hex-cols", "7", "--hex-group", "3"])
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(dump.status.success());
+        let output = Command::cargo_bin(PRG)?
+            .arg("--hex-reverse")
+            .write_stdin(dump.stdout)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_hex_dump() -> MyResult<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--hex-reverse")
+        .write_stdin("not a hex dump\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid hex dump at line 1"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_range_n() -> MyResult<()> {
+    run(
+        &["-n", "--range", "2:4,7:", BUSTLE],
+        "tests/expected/the-bustle.txt.n.range.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_range_b() -> MyResult<()> {
+    run(
+        &["-b", "--range", "4:6", BUSTLE],
+        "tests/expected/the-bustle.txt.b.range.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_range_n() -> MyResult<()> {
+    run(
+        &["-n", "--range", "2:2", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.range.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_range() -> MyResult<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--range", ":1", FOX, SPIDERS, BUSTLE])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "The quick brown fox jumps over the lazy dog.\n\
+         Don't worry, spiders,\n\
+         The bustle in a house\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> MyResult<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--range", "9:3", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "range start (9) must not be greater than its end (3)",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_utf16_auto() -> MyResult<()> {
+    for input in &[
+        "tests/inputs/the-bustle.utf16le.txt",
+        "tests/inputs/the-bustle.utf16be.txt",
+        "tests/inputs/the-bustle.utf16le-nobom.txt",
+    ] {
+        run(
+            &["--from-encoding", "auto", input],
+            "tests/expected/the-bustle.txt.out",
+        )?;
+        run(
+            &["--from-encoding", "auto", "-b", input],
+            "tests/expected/the-bustle.txt.b.out",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_utf16_label() -> MyResult<()> {
+    run(
+        &[
+            "--from-encoding",
+            "utf-16le",
+            "tests/inputs/the-bustle.utf16le.txt",
+        ],
+        "tests/expected/the-bustle.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1() -> MyResult<()> {
+    for encoding in &["latin1", "auto"] {
+        run(
+            &["--from-encoding", encoding, "tests/inputs/latin1.csv"],
+            "tests/expected/latin1.csv.out",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_stdin() -> MyResult<()> {
+    let input = fs::read("tests/inputs/latin1.csv")?;
+    let expected = fs::read_to_string("tests/expected/latin1.csv.out")?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["--from-encoding", "auto"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_encoding() -> MyResult<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "klingon", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown encoding -- klingon"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_reverse() -> MyResult<()> {
+    run(
+        &["--reverse", BUSTLE],
+        "tests/expected/the-bustle.txt.reverse.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_stdin_reverse() -> MyResult<()> {
+    run_stdin(
+        BUSTLE,
+        &["--reverse"],
+        "tests/expected/the-bustle.txt.reverse.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_reverse_n() -> MyResult<()> {
+    run(
+        &["--reverse", "-n", BUSTLE],
+        "tests/expected/the-bustle.txt.reverse.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_decompress_reverse() -> MyResult<()> {
+    run(
+        &["--reverse", "-z", "tests/inputs/the-bustle.txt.xz"],
+        "tests/expected/the-bustle.txt.reverse.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_reverse() -> MyResult<()> {
+    run_bytes(
+        &["--reverse", MIXED],
+        "tests/expected/mixed.bin.reverse.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn jet_reverse_separator_before() -> MyResult<()> {
+    run(
+        &["--reverse", "--separator", ".", "--before", JET],
+        "tests/expected/jet.txt.reverse.before.dot.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn jet_reverse_regex() -> MyResult<()> {
+    run(
+        &["--reverse", "--regex", "--separator", "[.:]", JET],
+        "tests/expected/jet.txt.reverse.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_empty_separator() -> MyResult<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "--separator", "", JET])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("separator cannot be empty"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn highlight_and_paging_ignored_when_piped() -> MyResult<()> {
+    run(
+        &["--highlight", "--paging", "-n", BUSTLE],
+        "tests/expected/the-bustle.txt.n.out",
+    )?;
+    run_bytes(
+        &["--highlight", "--language", "rust", "--paging", MIXED],
+        MIXED,
+    )
+}
@@ -1,7 +1,18 @@
-use clap::{value_parser, Arg, ArgAction, Command};
+mod parallel;
+mod split;
+
+use clap::{
+    builder::RangedU64ValueParser, parser::ValueSource, value_parser, Arg, ArgAction, ArgGroup,
+    Command,
+};
+use regex::bytes::Regex;
+use split::{Chunk, Split};
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
-use std::io::{stdin, BufRead, BufReader, Read};
+use std::io::{self, stdin, BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
 
 pub type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -10,11 +21,54 @@ pub struct Config {
     files: Vec<String>,
     lines: isize,
     bytes: Option<isize>,
+    chars: Option<isize>,
+    graphemes: bool,
+    quiet: bool,
+    verbose: bool,
+    zero_terminated: bool,
+    jobs: usize,
+    stop: Option<Stop>,
+    split: Option<Split>,
+}
+
+// print lines up to the first one matching `pattern`, with optional caps on
+// the number of lines and bytes printed when it never matches
+#[derive(Debug)]
+struct Stop {
+    pattern: Regex,
+    inclusive: bool,
+    max_lines: Option<usize>,
+    max_bytes: Option<u64>,
+}
+
+// powers of the multiplier suffixes accepted after a count, as in GNU head
+const SUFFIXES: &str = "KMGTPEZY";
+
+fn parse_multiplier(suffix: &str) -> Option<isize> {
+    let mut chars = suffix.chars();
+    let power = match chars.next() {
+        None => return Some(1),
+        Some('b') if suffix.len() == 1 => return Some(512),
+        // k and m may also be written in lower case
+        Some('k') => 1,
+        Some('m') => 2,
+        Some(prefix) => SUFFIXES.find(prefix)? as u32 + 1,
+    };
+    let base: isize = match chars.as_str() {
+        "" | "iB" => 1024,
+        "B" => 1000,
+        _ => return None,
+    };
+    base.checked_pow(power)
 }
 
 pub fn parse_positive_int(value: &str) -> MyResult<isize> {
-    match value.parse::<isize>() {
-        Ok(val) if val != 0 => Ok(val),
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit() && c != '-' && c != '+')
+        .unwrap_or(value.len());
+    let (number, suffix) = value.split_at(digits);
+    match (number.parse::<isize>(), parse_multiplier(suffix)) {
+        (Ok(val), Some(multiplier)) if val != 0 => val.checked_mul(multiplier).ok_or(value.into()),
         _ => Err(value.into()),
     }
 }
@@ -37,14 +91,35 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("BYTES")
                 .short('c')
                 .long("bytes")
-                .conflicts_with("lines")
+                .conflicts_with_all(["lines", "pattern"])
+                .action(ArgAction::Set)
+                .allow_hyphen_values(true)
+                .value_parser(|val: &str| match parse_positive_int(val) {
+                    Ok(v) => Ok(v),
+                    _ => Err("invalid digit found in string"),
+                })
+                .help("number of bytes to print, may have a multiplier suffix (b, K, KB, M, MiB, ...)"),
+        )
+        .arg(
+            Arg::new("chars")
+                .value_name("CHARS")
+                .short('m')
+                .long("chars")
+                .conflicts_with_all(["lines", "bytes", "pattern"])
                 .action(ArgAction::Set)
                 .allow_hyphen_values(true)
                 .value_parser(|val: &str| match parse_positive_int(val) {
                     Ok(v) => Ok(v),
                     _ => Err("invalid digit found in string"),
                 })
-                .help("number of bytes to print"),
+                .help("number of characters to print, may have a multiplier suffix"),
+        )
+        .arg(
+            Arg::new("graphemes")
+                .long("graphemes")
+                .requires("chars")
+                .action(ArgAction::SetTrue)
+                .help("count user-perceived characters (grapheme clusters) with -m"),
         )
         .arg(
             Arg::new("lines")
@@ -59,23 +134,176 @@ pub fn get_args() -> MyResult<Config> {
                 .num_args(1)
                 .allow_hyphen_values(true)
                 .action(ArgAction::Set)
-                .help("number of lines to print"),
+                .help("number of lines to print, may have a multiplier suffix (b, K, KB, M, MiB, ...)"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .visible_alias("silent")
+                .overrides_with("verbose")
+                .action(ArgAction::SetTrue)
+                .help("never print headers giving file names"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .overrides_with("quiet")
+                .action(ArgAction::SetTrue)
+                .help("always print headers giving file names"),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .action(ArgAction::SetTrue)
+                .help("line delimiter is NUL, not newline"),
+        )
+        .arg(
+            Arg::new("until")
+                .long("until")
+                .value_name("PATTERN")
+                .value_parser(parse_pattern)
+                .help("print lines up to, but not including, the first one matching PATTERN"),
+        )
+        .arg(
+            Arg::new("through")
+                .long("through")
+                .value_name("PATTERN")
+                .value_parser(parse_pattern)
+                .help("print lines up to and including the first one matching PATTERN"),
+        )
+        .group(ArgGroup::new("pattern").args(["until", "through"]))
+        .arg(
+            Arg::new("max_bytes")
+                .long("max-bytes")
+                .value_name("BYTES")
+                .requires("pattern")
+                .value_parser(|val: &str| match parse_positive_int(val) {
+                    Ok(v) if v > 0 => Ok(v as u64),
+                    _ => Err("invalid digit found in string"),
+                })
+                .help("print at most BYTES bytes when looking for PATTERN"),
+        )
+        .arg(
+            Arg::new("split")
+                .long("split")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["chars", "pattern"])
+                .help("keep going and write each chunk of LINES lines or BYTES bytes to its own file"),
+        )
+        .arg(
+            Arg::new("number")
+                .long("number")
+                .value_name("PARTS")
+                .requires("split")
+                .conflicts_with("bytes")
+                .value_parser(RangedU64ValueParser::<u64>::new().range(1..))
+                .help("split a regular file into PARTS files of about the same size"),
+        )
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .value_name("PREFIX")
+                .requires("split")
+                .default_value("x")
+                .help("prefix of the names of the split files"),
+        )
+        .arg(
+            Arg::new("suffix_length")
+                .long("suffix-length")
+                .value_name("N")
+                .requires("split")
+                .default_value("2")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .help("use suffixes of N letters for the names of the split files"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .default_value("1")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .help("read up to N files at the same time, the output keeps the order of FILES"),
         )
         .get_matches();
+    let lines = *matches
+        .get_one::<isize>("lines")
+        .expect("lines count unavailable");
+    let until = matches.get_one::<Regex>("until");
+    let through = matches.get_one::<Regex>("through");
+    let stop = match until.or(through) {
+        Some(pattern) => {
+            // -n is only a bound on the pattern search when it was given
+            let max_lines = match matches.value_source("lines") {
+                Some(ValueSource::DefaultValue) => None,
+                _ if lines < 0 => {
+                    return Err("--until and --through need a positive line count".into())
+                }
+                _ => Some(lines.unsigned_abs()),
+            };
+            Some(Stop {
+                pattern: pattern.clone(),
+                inclusive: through.is_some(),
+                max_lines,
+                max_bytes: matches.get_one::<u64>("max_bytes").copied(),
+            })
+        }
+        None => None,
+    };
+    let bytes = matches.get_one::<isize>("bytes").copied();
+    let files: Vec<String> = matches
+        .get_many::<String>("files")
+        .expect("at least one file should be specified")
+        .cloned()
+        .collect();
+    let split = match matches.get_flag("split") {
+        true => {
+            if files.len() > 1 {
+                return Err("--split takes a single input file".into());
+            }
+            let chunk = match (matches.get_one::<u64>("number"), bytes) {
+                (Some(parts), _) => Chunk::Number(*parts),
+                (None, Some(bytes)) if bytes > 0 => Chunk::Bytes(bytes as u64),
+                (None, None) if lines > 0 => Chunk::Lines(lines as usize),
+                _ => return Err("--split needs a positive count".into()),
+            };
+            Some(Split {
+                chunk,
+                prefix: matches
+                    .get_one::<String>("prefix")
+                    .expect("prefix should have a default")
+                    .clone(),
+                suffix_length: *matches
+                    .get_one::<usize>("suffix_length")
+                    .expect("suffix length should have a default"),
+            })
+        }
+        false => None,
+    };
     Ok(Config {
-        bytes: matches.get_one::<isize>("bytes").copied(),
-        files: matches
-            .get_many::<String>("files")
-            .expect("at least one file should be specified")
-            .map(|v| v.as_str().to_string())
-            .into_iter()
-            .collect(),
-        lines: *matches
-            .get_one::<isize>("lines")
-            .expect("lines count unavailable"),
+        bytes,
+        chars: matches.get_one::<isize>("chars").copied(),
+        graphemes: matches.get_flag("graphemes"),
+        files,
+        lines,
+        quiet: matches.get_flag("quiet"),
+        verbose: matches.get_flag("verbose"),
+        zero_terminated: matches.get_flag("zero_terminated"),
+        jobs: *matches
+            .get_one::<usize>("jobs")
+            .expect("jobs should have a default"),
+        stop,
+        split,
     })
 }
 
+fn parse_pattern(val: &str) -> Result<Regex, String> {
+    Regex::new(val).map_err(|err| err.to_string())
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(stdin()))),
@@ -83,90 +311,308 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn handle_lines(mut file: Box<dyn BufRead>, arg_lines: isize) -> MyResult<()> {
-    let mut buffer = String::new();
-    let mut lines: usize = 0;
+fn handle_lines(
+    mut file: Box<dyn BufRead>,
+    arg_lines: isize,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
     let requested_lines = arg_lines.unsigned_abs();
+    if arg_lines > 0 {
+        let mut buffer = Vec::new();
+        for _ in 0..requested_lines {
+            buffer.clear();
+            if file.read_until(delimiter, &mut buffer)? == 0 {
+                break;
+            }
+            out.write_all(&buffer)?;
+        }
+        return Ok(());
+    }
+    // hold back the last K lines, a line is printed once K newer ones were read
+    let mut ring: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut buffer = Vec::new();
     loop {
-        let bytes = file.read_line(&mut buffer)?;
-        if bytes == 0 {
+        if file.read_until(delimiter, &mut buffer)? == 0 {
             break;
         }
-        if arg_lines > 0 {
-            print!("{}", buffer);
-            buffer.clear();
+        ring.push_back(buffer);
+        buffer = if ring.len() > requested_lines {
+            let mut oldest = ring.pop_front().unwrap_or_default();
+            out.write_all(&oldest)?;
+            oldest.clear();
+            oldest
+        } else {
+            Vec::new()
+        };
+    }
+    Ok(())
+}
+
+fn handle_stop(
+    file: Box<dyn BufRead>,
+    stop: &Stop,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut file = file.take(stop.max_bytes.unwrap_or(u64::MAX));
+    let mut buffer = Vec::new();
+    for _ in 0..stop.max_lines.unwrap_or(usize::MAX) {
+        buffer.clear();
+        if file.read_until(delimiter, &mut buffer)? == 0 {
+            break;
         }
-        lines += 1;
-        if lines == arg_lines.unsigned_abs() && arg_lines > 0 {
+        let line = buffer.strip_suffix(&[delimiter]).unwrap_or(&buffer);
+        if stop.pattern.is_match(line) {
+            if stop.inclusive {
+                out.write_all(&buffer)?;
+            }
             break;
         }
+        out.write_all(&buffer)?;
     }
+    Ok(())
+}
 
-    if !buffer.is_empty() && lines > requested_lines {
-        buffer.split('\n').enumerate().for_each(
-            |(i, content)| {
-                if i < lines - requested_lines {
-                    print!("{}\n", content);
+// split bytes into characters or grapheme clusters, a byte that is not part
+// of valid UTF-8 counts as a character of its own
+fn split_chars(bytes: &[u8], graphemes: bool) -> impl Iterator<Item = &[u8]> {
+    bytes.utf8_chunks().flat_map(move |chunk| {
+        let valid = chunk.valid();
+        let chars: Box<dyn Iterator<Item = &[u8]>> = match graphemes {
+            true => Box::new(valid.graphemes(true).map(str::as_bytes)),
+            false => Box::new(
+                valid
+                    .char_indices()
+                    .map(|(i, c)| &valid.as_bytes()[i..i + c.len_utf8()]),
+            ),
+        };
+        chars.chain(chunk.invalid().chunks(1))
+    })
+}
+
+// how much of what was read so far can be split, the rest may still be
+// completed by the next read: a UTF-8 sequence cut short, or the last
+// grapheme cluster, which more combining characters could extend
+fn complete_len(bytes: &[u8], graphemes: bool) -> usize {
+    let Some(last) = bytes.utf8_chunks().last() else {
+        return 0;
+    };
+    let cut_short = str::from_utf8(last.invalid()).is_err_and(|err| err.error_len().is_none());
+    let mut len = bytes.len();
+    if cut_short {
+        len -= last.invalid().len();
+    }
+    if graphemes && (cut_short || last.invalid().is_empty()) {
+        len -= last.valid().graphemes(true).next_back().map_or(0, str::len);
+    }
+    len
+}
+
+// the input is read in blocks, so that only the characters being held back
+// are kept in memory however long the lines are
+fn handle_chars(
+    mut file: Box<dyn BufRead>,
+    arg_chars: isize,
+    graphemes: bool,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let requested_chars = arg_chars.unsigned_abs();
+    let mut pending = Vec::new();
+    // with a negative count, the bytes of the last K characters are held back
+    let mut ring: VecDeque<u8> = VecDeque::new();
+    let mut lengths: VecDeque<usize> = VecDeque::new();
+    let mut printed = 0;
+    loop {
+        let block = file.fill_buf()?;
+        let eof = block.is_empty();
+        pending.extend_from_slice(block);
+        let read = block.len();
+        file.consume(read);
+        let len = match eof {
+            true => pending.len(),
+            false => complete_len(&pending, graphemes),
+        };
+        for char in split_chars(&pending[..len], graphemes) {
+            if arg_chars > 0 {
+                out.write_all(char)?;
+                printed += 1;
+                if printed == requested_chars {
+                    return Ok(());
                 }
+                continue;
             }
-        );
+            ring.extend(char);
+            lengths.push_back(char.len());
+            if lengths.len() > requested_chars {
+                let len = lengths.pop_front().unwrap_or_default();
+                let (front, back) = ring.as_slices();
+                let split = len.min(front.len());
+                out.write_all(&front[..split])?;
+                out.write_all(&back[..len - split])?;
+                ring.drain(..len);
+            }
+        }
+        pending.drain(..len);
+        if eof {
+            return Ok(());
+        }
     }
-    Ok(())
 }
 
-fn handle_bytes(mut file: Box<dyn BufRead>, arg_bytes: isize) -> MyResult<()> {
-    let mut buffer: Vec<u8> = vec![];
-    let mut read_bytes: usize = 0;
+fn handle_bytes(
+    mut file: Box<dyn BufRead>,
+    arg_bytes: isize,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let requested_bytes = arg_bytes.unsigned_abs();
+    if arg_bytes > 0 {
+        io::copy(&mut file.take(requested_bytes as u64), out)?;
+        return Ok(());
+    }
+    // hold back the last K bytes, anything older is printed as soon as it is read
+    let mut ring: VecDeque<u8> = VecDeque::new();
+    let mut chunk = [0; 8192];
     loop {
-        let mut tmp: Vec<u8>;
-        let bytes: usize;
-        if arg_bytes > 0 {
-            tmp = vec![0; arg_bytes.unsigned_abs().try_into().unwrap()];
-            let mut handle = file.take(arg_bytes.unsigned_abs() as u64);
-            bytes = handle.read(&mut tmp)?;
-            read_bytes += bytes;
-            tmp[..bytes].into_iter().for_each(|&bit| buffer.push(bit));
+        let bytes = file.read(&mut chunk)?;
+        if bytes == 0 {
             break;
-        } else {
-            let abs_bytes = arg_bytes.unsigned_abs();
-            tmp = vec![0; 128];
-            bytes = file.read(&mut tmp)?;
-            read_bytes += bytes;
-            tmp[..bytes].into_iter().for_each(|&bit| buffer.push(bit));
-            if bytes == 0 {
-                read_bytes = if read_bytes > abs_bytes {read_bytes - abs_bytes} else {0};
-                break;
-            }
         }
-    }
-    if read_bytes > 0 {
-        print!("{}", String::from_utf8_lossy(&buffer[..read_bytes]));
+        ring.extend(&chunk[..bytes]);
+        if ring.len() > requested_bytes {
+            let excess = ring.len() - requested_bytes;
+            let (front, back) = ring.as_slices();
+            let split = excess.min(front.len());
+            out.write_all(&front[..split])?;
+            out.write_all(&back[..excess - split])?;
+            ring.drain(..excess);
+        }
     }
     Ok(())
 }
+
+impl Config {
+    // true when the output of a file is capped by a count, a negative count or
+    // a pattern without caps can be most of the file, which is better
+    // streamed than read ahead into memory
+    fn is_bounded(&self) -> bool {
+        match (&self.stop, self.chars, self.bytes) {
+            (Some(stop), _, _) => stop.max_lines.is_some() || stop.max_bytes.is_some(),
+            (None, Some(chars), _) => chars > 0,
+            (None, None, Some(bytes)) => bytes > 0,
+            (None, None, None) => self.lines > 0,
+        }
+    }
+}
+
+fn head_file(
+    file: Box<dyn BufRead>,
+    config: &Config,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    if let Some(stop) = &config.stop {
+        handle_stop(file, stop, delimiter, out)
+    } else if let Some(chars_count) = config.chars {
+        handle_chars(file, chars_count, config.graphemes, out)
+    } else if let Some(bytes_count) = config.bytes {
+        handle_bytes(file, bytes_count, out)
+    } else {
+        handle_lines(file, config.lines, delimiter, out)
+    }
+}
+
+// head_file reads and writes in the same loop, when it fails this tells a
+// closed or full stdout, which ends the run, from a file that broke off
+// halfway and only counts as one failed input
+struct Output<W> {
+    inner: W,
+    failed: bool,
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|_| self.failed = true)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|_| self.failed = true)
+    }
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let files_count = config.files.len();
-    for (index, filename) in config.files.into_iter().enumerate() {
-        if files_count > 1 {
-            println!("{}==> {} <==", if index > 0 { "\n" } else { "" }, filename);
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    if let Some(split) = &config.split {
+        return split::split(&config.files[0], split, delimiter);
+    }
+    let config = Arc::new(config);
+    // with several jobs the files are read ahead on worker threads, the
+    // output of each one is buffered until its turn comes
+    let mut previews = match config.jobs {
+        jobs if jobs > 1 && config.is_bounded() => {
+            Some(parallel::previews(config.clone(), jobs, delimiter))
+        }
+        _ => None,
+    };
+    let stdout = io::stdout();
+    let mut out = Output {
+        inner: BufWriter::new(stdout.lock()),
+        failed: false,
+    };
+    // -q and -v override each other, the last one given wins
+    let headers = config.verbose || (!config.quiet && config.files.len() > 1);
+    let mut first_header = true;
+    let mut header = |out: &mut Output<_>, filename: &str| -> io::Result<()> {
+        if headers {
+            let separator = if first_header { "" } else { "\n" };
+            writeln!(out, "{}==> {} <==", separator, filename)?;
+            first_header = false;
         }
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to Open {}: {}", filename, err),
-            Ok(file) => {
-                if let Some(bytes_count) = config.bytes {
-                    handle_bytes(file, bytes_count)?;
-                } else {
-                    handle_lines(file, config.lines)?;
+        Ok(())
+    };
+    let mut failures = 0;
+    for filename in &config.files {
+        let preview = previews
+            .as_mut()
+            .map(|previews| previews.next().expect("every file should have a preview"));
+        let err = match preview {
+            Some(Err(err)) => err,
+            Some(Ok((buffer, err))) => {
+                header(&mut out, filename)?;
+                out.write_all(&buffer)?;
+                match err {
+                    Some(err) => err,
+                    None => continue,
                 }
             }
-        }
+            None => match open(filename) {
+                Err(err) => format!("Failed to open {}: {}", filename, err),
+                Ok(file) => {
+                    header(&mut out, filename)?;
+                    match head_file(file, &config, delimiter, &mut out) {
+                        Err(err) if out.failed => return Err(err),
+                        Err(err) => format!("Failed to read {}: {}", filename, err),
+                        Ok(()) => continue,
+                    }
+                }
+            },
+        };
+        // keep the error next to the output of the files before it
+        out.flush()?;
+        eprintln!("{}", err);
+        failures += 1;
+    }
+    out.flush()?;
+    if failures > 0 {
+        return Err(format!("{} of {} inputs failed", failures, config.files.len()).into());
     }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_positive_int;
+    use super::{complete_len, handle_chars, parse_positive_int, split_chars};
+    use std::io::BufReader;
 
     #[test]
     fn test_parse_positive_int() {
@@ -176,6 +622,80 @@ mod tests {
         assert_eq!(res.unwrap(), 3);
         res = parse_positive_int("foo");
         assert!(res.is_err());
-        assert_eq!(res.unwrap_err().to_string(), "foo".to_string())
+        assert_eq!(res.unwrap_err().to_string(), "foo".to_string());
+        // negative counts keep their sign
+        assert_eq!(parse_positive_int("-3").unwrap(), -3);
+        // zero is not a valid count
+        assert!(parse_positive_int("0").is_err());
+        assert!(parse_positive_int("0K").is_err());
+        // multiplier suffixes
+        assert_eq!(parse_positive_int("2b").unwrap(), 1024);
+        assert_eq!(parse_positive_int("1kB").unwrap(), 1000);
+        assert_eq!(parse_positive_int("1K").unwrap(), 1024);
+        assert_eq!(parse_positive_int("1k").unwrap(), 1024);
+        assert_eq!(parse_positive_int("3KiB").unwrap(), 3 * 1024);
+        assert_eq!(parse_positive_int("-2MB").unwrap(), -2_000_000);
+        assert_eq!(parse_positive_int("1M").unwrap(), 1 << 20);
+        assert_eq!(parse_positive_int("1MiB").unwrap(), 1 << 20);
+        assert_eq!(parse_positive_int("1G").unwrap(), 1 << 30);
+        assert_eq!(parse_positive_int("1GB").unwrap(), 1_000_000_000);
+        assert_eq!(parse_positive_int("1E").unwrap(), 1 << 60);
+        // unknown suffixes and overflows are errors
+        assert!(parse_positive_int("1X").is_err());
+        assert!(parse_positive_int("1KiBB").is_err());
+        assert!(parse_positive_int("K").is_err());
+        assert!(parse_positive_int("1Z").is_err());
+        assert!(parse_positive_int("8E").is_err());
+    }
+
+    #[test]
+    fn test_split_chars() {
+        let line = "e\u{301}t\u{e9} \u{1F44D}\u{1F3FD}\n".as_bytes();
+        assert_eq!(split_chars(line, false).count(), 8);
+        assert_eq!(
+            split_chars(line, true).collect::<Vec<_>>(),
+            vec![
+                "e\u{301}".as_bytes(),
+                b"t",
+                "\u{e9}".as_bytes(),
+                b" ",
+                "\u{1F44D}\u{1F3FD}".as_bytes(),
+                b"\n"
+            ]
+        );
+        // invalid bytes are counted one at a time and kept as they are
+        assert_eq!(
+            split_chars(b"a\xff\xfe\xc3\xa9", false).collect::<Vec<_>>(),
+            vec![&b"a"[..], b"\xff", b"\xfe", "\u{e9}".as_bytes()]
+        );
+    }
+
+    #[test]
+    fn test_complete_len() {
+        // a sequence cut short waits for the rest of its bytes
+        assert_eq!(complete_len(b"ab\xe2\x82", false), 2);
+        assert_eq!(complete_len(b"ab\xff", false), 3);
+        // the last grapheme cluster waits in case it goes on
+        assert_eq!(complete_len(b"abe", true), 2);
+        assert_eq!(complete_len("ae\u{301}".as_bytes(), true), 1);
+        assert_eq!(complete_len(b"ae\xcc", true), 1);
+        assert_eq!(complete_len(b"", true), 0);
+    }
+
+    #[test]
+    fn test_handle_chars_across_reads() {
+        // a tiny buffer cuts every character and cluster between two reads
+        let text = "e\u{301}t\u{e9} \u{1F44D}\u{1F3FD}\r\n\u{e9}";
+        let head = |count, graphemes| {
+            let file = Box::new(BufReader::with_capacity(3, text.as_bytes()));
+            let mut out = Vec::new();
+            handle_chars(file, count, graphemes, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(head(2, false), "e\u{301}");
+        assert_eq!(head(2, true), "e\u{301}t");
+        assert_eq!(head(5, true), "e\u{301}t\u{e9} \u{1F44D}\u{1F3FD}");
+        assert_eq!(head(-2, true), "e\u{301}t\u{e9} \u{1F44D}\u{1F3FD}");
+        assert_eq!(head(-3, false), "e\u{301}t\u{e9} \u{1F44D}\u{1F3FD}");
     }
 }
@@ -0,0 +1,73 @@
+use crate::{head_file, open, Config};
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// the output of a file read ahead by a worker along with the error that cut
+// it short, or why the file could not be opened
+pub type Preview = Result<(Vec<u8>, Option<String>), String>;
+
+fn preview(filename: &str, config: &Config, delimiter: u8) -> Preview {
+    let file = open(filename).map_err(|err| format!("Failed to open {}: {}", filename, err))?;
+    let mut buffer = Vec::new();
+    let err = head_file(file, config, delimiter, &mut buffer)
+        .err()
+        .map(|err| format!("Failed to read {}: {}", filename, err));
+    Ok((buffer, err))
+}
+
+// a file to read, with where to send its preview
+type Task = (usize, SyncSender<Preview>);
+
+// yields the previews of the files in command-line order, whatever the order
+// the workers finish them in
+pub struct Previews {
+    tasks: Sender<Task>,
+    // one receiver per file handed out, oldest first
+    pending: VecDeque<Receiver<Preview>>,
+    next: usize,
+    files: usize,
+    jobs: usize,
+}
+
+impl Iterator for Previews {
+    type Item = Preview;
+
+    fn next(&mut self) -> Option<Preview> {
+        // no more than `jobs` files are handed out ahead of the one being
+        // written, so that a slow file cannot make the others pile up
+        while self.pending.len() < self.jobs && self.next < self.files {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            self.tasks.send((self.next, sender)).ok()?;
+            self.pending.push_back(receiver);
+            self.next += 1;
+        }
+        self.pending.pop_front()?.recv().ok()
+    }
+}
+
+// read the files on `jobs` threads, each worker takes the next file that was
+// handed out so that a slow file does not hold the others back
+pub fn previews(config: Arc<Config>, jobs: usize, delimiter: u8) -> Previews {
+    let (tasks, receiver) = mpsc::channel::<Task>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..jobs.min(config.files.len()) {
+        let (config, receiver) = (config.clone(), receiver.clone());
+        thread::spawn(move || loop {
+            // the sender is gone once the previews are not wanted anymore
+            let task = receiver.lock().map(|receiver| receiver.recv());
+            let Ok(Ok((index, sender))) = task else {
+                break;
+            };
+            let _ = sender.send(preview(&config.files[index], &config, delimiter));
+        });
+    }
+    Previews {
+        tasks,
+        pending: VecDeque::new(),
+        next: 0,
+        files: config.files.len(),
+        jobs,
+    }
+}
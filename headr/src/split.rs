@@ -0,0 +1,133 @@
+use crate::{open, MyResult};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Read, Write};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Chunk {
+    Lines(usize),
+    Bytes(u64),
+    // a number of parts of about the same size
+    Number(u64),
+}
+
+#[derive(Debug)]
+pub struct Split {
+    pub chunk: Chunk,
+    pub prefix: String,
+    pub suffix_length: usize,
+}
+
+impl Split {
+    // output files are named like split does: PREFIXaa, PREFIXab, ..., PREFIXzz
+    fn filename(&self, index: u64) -> MyResult<String> {
+        let mut suffix = vec![b'a'; self.suffix_length];
+        let mut rest = index;
+        for letter in suffix.iter_mut().rev() {
+            *letter += (rest % 26) as u8;
+            rest /= 26;
+        }
+        if rest > 0 {
+            return Err("output file suffixes exhausted".into());
+        }
+        Ok(format!(
+            "{}{}",
+            self.prefix,
+            String::from_utf8_lossy(&suffix)
+        ))
+    }
+
+    fn create(&self, index: u64) -> MyResult<BufWriter<File>> {
+        let filename = self.filename(index)?;
+        let file = File::create(&filename)
+            .map_err(|err| format!("Failed to create {}: {}", filename, err))?;
+        Ok(BufWriter::new(file))
+    }
+}
+
+// write the input to successive output files instead of stopping after the
+// first chunk, no file is created for an empty input
+pub fn split(filename: &str, split: &Split, delimiter: u8) -> MyResult<()> {
+    let file = open(filename).map_err(|err| format!("Failed to open {}: {}", filename, err))?;
+    match split.chunk {
+        Chunk::Lines(lines) => split_lines(file, split, lines, delimiter),
+        Chunk::Bytes(bytes) => split_bytes(file, split, bytes),
+        Chunk::Number(parts) => split_number(file, filename, split, parts),
+    }
+}
+
+fn split_lines(
+    mut file: Box<dyn BufRead>,
+    split: &Split,
+    lines: usize,
+    delimiter: u8,
+) -> MyResult<()> {
+    let mut buffer = Vec::new();
+    let mut index = 0;
+    while !file.fill_buf()?.is_empty() {
+        let mut out = split.create(index)?;
+        for _ in 0..lines {
+            buffer.clear();
+            if file.read_until(delimiter, &mut buffer)? == 0 {
+                break;
+            }
+            out.write_all(&buffer)?;
+        }
+        out.flush()?;
+        index += 1;
+    }
+    Ok(())
+}
+
+fn split_bytes(mut file: Box<dyn BufRead>, split: &Split, bytes: u64) -> MyResult<()> {
+    let mut index = 0;
+    while !file.fill_buf()?.is_empty() {
+        let mut out = split.create(index)?;
+        io::copy(&mut file.by_ref().take(bytes), &mut out)?;
+        out.flush()?;
+        index += 1;
+    }
+    Ok(())
+}
+
+// every part gets size / parts bytes (at least one) and the last one also takes
+// the rest, as with split -n, all the parts are created even when empty
+fn split_number(
+    mut file: Box<dyn BufRead>,
+    filename: &str,
+    split: &Split,
+    parts: u64,
+) -> MyResult<()> {
+    let size = match fs::metadata(filename) {
+        Ok(metadata) if filename != "-" && metadata.is_file() => metadata.len(),
+        _ => return Err(format!("{}: --number needs a regular file", filename).into()),
+    };
+    for index in 0..parts {
+        let part = match index + 1 == parts {
+            true => u64::MAX,
+            false => (size / parts).max(1),
+        };
+        let mut out = split.create(index)?;
+        io::copy(&mut file.by_ref().take(part), &mut out)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chunk, Split};
+
+    #[test]
+    fn test_filename() {
+        let split = Split {
+            chunk: Chunk::Lines(1),
+            prefix: "x".to_string(),
+            suffix_length: 2,
+        };
+        assert_eq!(split.filename(0).unwrap(), "xaa");
+        assert_eq!(split.filename(1).unwrap(), "xab");
+        assert_eq!(split.filename(26).unwrap(), "xba");
+        assert_eq!(split.filename(675).unwrap(), "xzz");
+        assert!(split.filename(676).is_err());
+    }
+}
@@ -2,8 +2,7 @@ use headr::MyResult as Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs::{self, File};
-use std::io::prelude::*;
+use std::fs;
 
 const PRG: &str = "headr";
 const EMPTY: &str = "./tests/inputs/empty.txt";
@@ -11,6 +10,10 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TWELVE: &str = "./tests/inputs/twelve.txt";
+const BINARY: &str = "./tests/inputs/binary.bin";
+const NUL: &str = "./tests/inputs/nul.txt";
+const PREAMBLE: &str = "./tests/inputs/preamble.log";
+const MULTILINGUAL: &str = "./tests/inputs/multilingual.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -89,6 +92,7 @@ fn skips_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .args([EMPTY, &bad, ONE])
         .assert()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
 
     Ok(())
@@ -96,15 +100,10 @@ fn skips_bad_file() -> Result<()> {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-
+    let expected = fs::read(expected_file)?;
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
     assert!(output.status.success());
-    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert_eq!(output.stdout, expected);
 
     Ok(())
 }
@@ -115,12 +114,8 @@ fn run_stdin(
     input_file: &str,
     expected_file: &str,
 ) -> Result<()> {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-    let input = fs::read_to_string(input_file)?;
+    let expected = fs::read(expected_file)?;
+    let input = fs::read(input_file)?;
 
     let output = Command::cargo_bin(PRG)?
         .write_stdin(input)
@@ -128,7 +123,7 @@ fn run_stdin(
         .output()
         .expect("fail");
     assert!(output.status.success());
-    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert_eq!(output.stdout, expected);
 
     Ok(())
 }
@@ -438,3 +433,491 @@ fn multiple_files_c_neg_10() -> Result<()> {
         "tests/expected/all.c-10.out"
     )
 }
+
+#[test]
+fn twelve_n_neg_3() -> Result<()> {
+    run(&[TWELVE, "-n", "-3"], "tests/expected/twelve.txt.n-3.out")
+}
+
+#[test]
+fn twelve_n_neg_20() -> Result<()> {
+    run(&[TWELVE, "-n", "-20"], "tests/expected/twelve.txt.n-20.out")
+}
+
+#[test]
+fn twelve_c_neg_5_stdin() -> Result<()> {
+    run_stdin(&["-c", "-5"], TWELVE, "tests/expected/twelve.txt.c-5.out")
+}
+
+#[test]
+fn large_stdin_n_neg() -> Result<()> {
+    let input: String = (1..=100_000).map(|i| format!("{i}\n")).collect();
+    let expected: String = (1..=99_990).map(|i| format!("{i}\n")).collect();
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .args(["-n", "-10"])
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .args(["-c", "-10000"])
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        input[..input.len() - 10_000]
+    );
+    Ok(())
+}
+
+#[test]
+fn binary_c2() -> Result<()> {
+    run(&[BINARY, "-c", "2"], "tests/expected/binary.bin.c2.out")
+}
+
+#[test]
+fn binary_c_neg_1_stdin() -> Result<()> {
+    run_stdin(&["-c", "-1"], BINARY, "tests/expected/binary.bin.c-1.out")
+}
+
+#[test]
+fn binary_n2() -> Result<()> {
+    run(&[BINARY, "-n", "2"], "tests/expected/binary.bin.n2.out")
+}
+
+#[test]
+fn binary_n_neg_1() -> Result<()> {
+    run(&[BINARY, "-n", "-1"], "tests/expected/binary.bin.n-1.out")
+}
+
+#[test]
+fn suffix_c_1k() -> Result<()> {
+    run(&[BINARY, "-c", "1K"], "tests/expected/binary.bin.c1K.out")
+}
+
+#[test]
+fn suffix_c_neg_1kb() -> Result<()> {
+    run(
+        &[BINARY, "-c", "-1kB"],
+        "tests/expected/binary.bin.c-1kB.out",
+    )
+}
+
+#[test]
+fn suffix_n_neg_1b() -> Result<()> {
+    run(&[BINARY, "-n", "-1b"], "tests/expected/binary.bin.n-1b.out")
+}
+
+#[test]
+fn dies_bad_suffix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1X", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '1X' for '--bytes <BYTES>'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn zero_terminated_n2() -> Result<()> {
+    run(&["-z", "-n", "2", NUL], "tests/expected/nul.txt.z.n2.out")
+}
+
+#[test]
+fn zero_terminated_n_neg_2_stdin() -> Result<()> {
+    run_stdin(&["-z", "-n", "-2"], NUL, "tests/expected/nul.txt.z.n-2.out")
+}
+
+#[test]
+fn newline_terminated_nul_input() -> Result<()> {
+    run(&["-n", "2", NUL], "tests/expected/nul.txt.n2.out")
+}
+
+#[test]
+fn quiet_multiple_files() -> Result<()> {
+    run(
+        &["-q", "-n", "1", ONE, TWO, THREE],
+        "tests/expected/all.q.n1.out",
+    )
+}
+
+#[test]
+fn silent_multiple_files() -> Result<()> {
+    run(
+        &["--silent", "-n", "1", ONE, TWO, THREE],
+        "tests/expected/all.q.n1.out",
+    )
+}
+
+#[test]
+fn verbose_single_file() -> Result<()> {
+    run(&["-v", ONE], "tests/expected/one.txt.v.out")
+}
+
+#[test]
+fn quiet_then_verbose() -> Result<()> {
+    run(
+        &["-q", "-v", "-n", "1", ONE, TWO],
+        "tests/expected/all.q.v.n1.out",
+    )
+}
+
+#[test]
+fn verbose_then_quiet() -> Result<()> {
+    run(
+        &["-v", "-q", "-n", "1", ONE, TWO],
+        "tests/expected/all.v.q.n1.out",
+    )
+}
+
+#[test]
+fn until_pattern() -> Result<()> {
+    run(
+        &["--until", "^---$", PREAMBLE],
+        "tests/expected/preamble.log.until.out",
+    )
+}
+
+#[test]
+fn through_pattern_stdin() -> Result<()> {
+    run_stdin(
+        &["--through", "^---$"],
+        PREAMBLE,
+        "tests/expected/preamble.log.through.out",
+    )
+}
+
+#[test]
+fn until_pattern_bounded_by_lines() -> Result<()> {
+    run(
+        &["--until", "^---$", "-n", "2", PREAMBLE],
+        "tests/expected/preamble.log.until.n2.out",
+    )
+}
+
+#[test]
+fn until_pattern_ignores_default_lines() -> Result<()> {
+    let input: String = (1..=20).map(|i| format!("{i}\n")).collect();
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(["--until", "^15$"])
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    let expected: String = (1..=14).map(|i| format!("{i}\n")).collect();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    Ok(())
+}
+
+#[test]
+fn through_pattern_max_bytes() -> Result<()> {
+    run(
+        &["--through", "no such line", "--max-bytes", "30", PREAMBLE],
+        "tests/expected/preamble.log.through.max-bytes.out",
+    )
+}
+
+#[test]
+fn dies_until_and_through() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "a", "--through", "b", PREAMBLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn dies_max_bytes_without_pattern() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--max-bytes", "10", PREAMBLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_pattern() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "(", PREAMBLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '(' for '--until <PATTERN>'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn chars_m4() -> Result<()> {
+    run(
+        &["-m", "4", MULTILINGUAL],
+        "tests/expected/multilingual.txt.m4.out",
+    )
+}
+
+#[test]
+fn chars_m14() -> Result<()> {
+    run(
+        &["-m", "14", MULTILINGUAL],
+        "tests/expected/multilingual.txt.m14.out",
+    )
+}
+
+#[test]
+fn chars_m_neg_5_stdin() -> Result<()> {
+    run_stdin(
+        &["--chars", "-5"],
+        MULTILINGUAL,
+        "tests/expected/multilingual.txt.m-5.out",
+    )
+}
+
+#[test]
+fn graphemes_m21() -> Result<()> {
+    run(
+        &["-m", "21", "--graphemes", MULTILINGUAL],
+        "tests/expected/multilingual.txt.m-graphemes21.out",
+    )
+}
+
+#[test]
+fn graphemes_m_neg_3() -> Result<()> {
+    run(
+        &["-m", "-3", "--graphemes", MULTILINGUAL],
+        "tests/expected/multilingual.txt.m-graphemes-3.out",
+    )
+}
+
+#[test]
+fn dies_chars_and_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "1", "-c", "2", MULTILINGUAL])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn mixed_good_and_missing_files() -> Result<()> {
+    let bad1 = gen_bad_file();
+    let bad2 = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/one.two.n1.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", &bad1, ONE, &bad2, TWO])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::is_match(format!(
+            "Failed to open {bad1}: .* [(]os error 2[)]\n\
+             Failed to open {bad2}: .* [(]os error 2[)]\n\
+             2 of 4 inputs failed\n$"
+        ))?);
+
+    Ok(())
+}
+
+#[test]
+fn unreadable_file_continues() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/dir.one.n1.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", "tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::is_match(
+            "Failed to read tests/inputs: .* [(]os error 21[)]\n\
+             1 of 2 inputs failed\n$",
+        )?);
+
+    Ok(())
+}
+
+#[test]
+fn split_lines() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prefix = dir.path().join("x");
+    Command::cargo_bin(PRG)?
+        .args([
+            "--split",
+            "-n",
+            "5",
+            "--prefix",
+            prefix.to_str().unwrap(),
+            TWELVE,
+        ])
+        .assert()
+        .success()
+        .stdout("");
+    let input = fs::read_to_string(TWELVE)?;
+    let lines: Vec<&str> = input.split_inclusive('\n').collect();
+    for (suffix, chunk) in ["aa", "ab", "ac"].iter().zip(lines.chunks(5)) {
+        let part = fs::read_to_string(dir.path().join(format!("x{suffix}")))?;
+        assert_eq!(part, chunk.concat());
+    }
+    assert_eq!(fs::read_dir(dir.path())?.count(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn split_bytes_prefix_suffix_length() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prefix = dir.path().join("part-");
+    Command::cargo_bin(PRG)?
+        .args([
+            "--split",
+            "-c",
+            "1K",
+            "--suffix-length",
+            "3",
+            "--prefix",
+            prefix.to_str().unwrap(),
+            BINARY,
+        ])
+        .assert()
+        .success();
+    let input = fs::read(BINARY)?;
+    let suffixes = ["aaa", "aab", "aac", "aad", "aae"];
+    for (suffix, chunk) in suffixes.iter().zip(input.chunks(1024)) {
+        assert_eq!(fs::read(dir.path().join(format!("part-{suffix}")))?, chunk);
+    }
+    assert_eq!(fs::read_dir(dir.path())?.count(), 5);
+
+    Ok(())
+}
+
+#[test]
+fn split_number() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prefix = dir.path().join("x");
+    Command::cargo_bin(PRG)?
+        .args([
+            "--split",
+            "--number",
+            "3",
+            "--prefix",
+            prefix.to_str().unwrap(),
+            BINARY,
+        ])
+        .assert()
+        .success();
+    let input = fs::read(BINARY)?;
+    let part = input.len() / 3;
+    assert_eq!(fs::read(dir.path().join("xaa"))?, &input[..part]);
+    assert_eq!(fs::read(dir.path().join("xab"))?, &input[part..2 * part]);
+    assert_eq!(fs::read(dir.path().join("xac"))?, &input[2 * part..]);
+
+    Ok(())
+}
+
+#[test]
+fn split_empty_input() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prefix = dir.path().join("x");
+    Command::cargo_bin(PRG)?
+        .args(["--split", "--prefix", prefix.to_str().unwrap(), EMPTY])
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn dies_split_number_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--split", "--number", "2"])
+        .write_stdin("abc\n")
+        .assert()
+        .failure()
+        .stderr("-: --number needs a regular file\n");
+
+    Ok(())
+}
+
+#[test]
+fn dies_split_multiple_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--split", ONE, TWO])
+        .assert()
+        .failure()
+        .stderr("--split takes a single input file\n");
+
+    Ok(())
+}
+
+#[test]
+fn jobs_multiple_files_n2() -> Result<()> {
+    run(
+        &[EMPTY, ONE, TWO, THREE, TWELVE, "-n", "2", "-j", "3"],
+        "tests/expected/all.n2.out",
+    )
+}
+
+#[test]
+fn jobs_multiple_files_c_neg_10() -> Result<()> {
+    run(
+        &[EMPTY, ONE, TWO, THREE, TWELVE, "-c", "-10", "--jobs", "8"],
+        "tests/expected/all.c-10.out",
+    )
+}
+
+#[test]
+fn jobs_keep_order_of_many_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut files = vec![];
+    for i in 0..200 {
+        let path = dir.path().join(format!("{i}.txt"));
+        let text: String = (i..i + 10).map(|n| format!("{n}\n")).collect();
+        fs::write(&path, text)?;
+        files.push(path.to_str().unwrap().to_string());
+    }
+    let bad = gen_bad_file();
+    files.insert(100, bad.clone());
+    let mut args = vec!["-n".to_string(), "3".to_string()];
+    args.extend(files);
+
+    let sequential = Command::cargo_bin(PRG)?.args(&args).output()?;
+    let parallel = Command::cargo_bin(PRG)?
+        .args(&args)
+        .args(["-j", "16"])
+        .output()?;
+    assert!(!parallel.status.success());
+    assert_eq!(parallel.stdout, sequential.stdout);
+    assert_eq!(
+        String::from_utf8_lossy(&parallel.stderr),
+        String::from_utf8_lossy(&sequential.stderr)
+    );
+    assert!(String::from_utf8_lossy(&parallel.stderr).contains(&bad));
+
+    Ok(())
+}
+
+#[test]
+fn dies_zero_jobs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '0' for '--jobs <N>'",
+        ));
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use crate::MyResult;
+use inotify::{Inotify, WatchMask};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FollowMode {
+    // keep reading the file that was opened, even after it was renamed
+    Descriptor,
+    // keep reading whatever file has the name, reopening it after rotation
+    Name,
+}
+
+#[derive(Debug, Clone)]
+pub struct Follow {
+    pub mode: FollowMode,
+    pub retry: bool,
+    // stop once this process has exited
+    pub pid: Option<u32>,
+    pub sleep_interval: Duration,
+    pub inotify: bool,
+}
+
+struct Followed {
+    // position among all the inputs, stdin and pipes included
+    index: usize,
+    name: String,
+    file: Option<File>,
+    // device and inode of the open file, a different pair behind the name
+    // means the file was replaced
+    id: Option<(u64, u64)>,
+    pos: u64,
+}
+
+impl Followed {
+    fn new(index: usize, name: String, mut file: Option<File>) -> io::Result<Self> {
+        let (id, pos) = match file.as_mut() {
+            Some(file) => {
+                let metadata = file.metadata()?;
+                // the tail leaves the file where it stopped reading, what was
+                // appended since then is still to be printed
+                (
+                    Some((metadata.dev(), metadata.ino())),
+                    file.stream_position()?,
+                )
+            }
+            None => (None, 0),
+        };
+        Ok(Followed {
+            index,
+            name,
+            file,
+            id,
+            pos,
+        })
+    }
+
+    // notice a name that now points to another file, or to nothing, what was
+    // still appended to the old file is printed before letting go of it
+    fn check_name(&mut self, printer: &mut Printer) -> MyResult<()> {
+        let reopened = fs::metadata(&self.name).and_then(|metadata| {
+            let id = (metadata.dev(), metadata.ino());
+            match self.id == Some(id) {
+                true => Ok(None),
+                false => File::open(&self.name).map(|file| Some((file, id))),
+            }
+        });
+        match reopened {
+            Ok(None) => {}
+            Ok(Some((file, id))) => {
+                self.read_new(printer)?;
+                match self.id {
+                    Some(_) => eprintln!("'{}' has been replaced; following new file", self.name),
+                    None => eprintln!("'{}' has appeared; following new file", self.name),
+                }
+                self.file = Some(file);
+                self.id = Some(id);
+                self.pos = 0;
+            }
+            Err(err) => {
+                self.read_new(printer)?;
+                if self.file.take().is_some() {
+                    eprintln!("'{}' has become inaccessible: {}", self.name, err);
+                }
+                self.id = None;
+            }
+        }
+        Ok(())
+    }
+
+    // print whatever was appended since the last look
+    fn read_new(&mut self, printer: &mut Printer) -> MyResult<()> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.pos {
+            eprintln!("{}: file truncated", self.name);
+            self.pos = 0;
+        }
+        if len == self.pos {
+            return Ok(());
+        }
+        file.seek(SeekFrom::Start(self.pos))?;
+        let mut data = Vec::new();
+        (&*file).take(len - self.pos).read_to_end(&mut data)?;
+        self.pos += data.len() as u64;
+        printer.print(self.index, &self.name, &data)?;
+        Ok(())
+    }
+}
+
+// prints a header whenever the output switches to another file
+struct Printer {
+    headers: bool,
+    last: Option<usize>,
+}
+
+impl Printer {
+    fn print(&mut self, index: usize, name: &str, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut out = io::stdout().lock();
+        if self.headers && self.last != Some(index) {
+            let separator = if self.last.is_some() { "\n" } else { "" };
+            writeln!(out, "{}==> {} <==", separator, name)?;
+        }
+        self.last = Some(index);
+        out.write_all(data)?;
+        out.flush()
+    }
+}
+
+// keep printing what gets appended to the files after their tail was printed,
+// files are known by their position on the command line, which is also what
+// `last` gives for the file whose header was printed last
+pub fn follow(
+    follow: &Follow,
+    files: Vec<(usize, String, Option<File>)>,
+    headers: bool,
+    last: Option<usize>,
+) -> MyResult<()> {
+    let mut followed = Vec::new();
+    for (index, name, file) in files {
+        followed.push(Followed::new(index, name, file)?);
+    }
+    let mut printer = Printer { headers, last };
+    let mut watcher = Watcher::new(follow);
+    for file in &followed {
+        watcher.watch(&file.name, follow);
+    }
+    loop {
+        // checked before reading so that what the process wrote last is printed
+        let exited = follow.pid.is_some_and(|pid| !is_running(pid));
+        for file in &mut followed {
+            // a descriptor is only looked for by name until it could be opened
+            if follow.mode == FollowMode::Name || (follow.retry && file.file.is_none()) {
+                let id = file.id;
+                file.check_name(&mut printer)?;
+                if file.id.is_some() && file.id != id {
+                    watcher.watch(&file.name, follow);
+                }
+            }
+        }
+        // a file is given up on once it cannot be read anymore, unless retrying
+        followed.retain(|file| file.file.is_some() || follow.retry);
+        if followed.is_empty() {
+            return Err("no files remaining".into());
+        }
+        for file in &mut followed {
+            file.read_new(&mut printer)?;
+        }
+        if exited {
+            return Ok(());
+        }
+        watcher.wait(follow.sleep_interval);
+    }
+}
+
+// a process that exited but was not reaped yet keeps its entry in /proc, as a
+// zombie
+fn is_running(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid)).is_ok_and(|stat| {
+        stat.rsplit_once(')')
+            .is_some_and(|(_, rest)| !rest.trim_start().starts_with(['Z', 'X']))
+    })
+}
+
+// waits for the files to change, with inotify when it is available and by
+// sleeping for the interval otherwise
+enum Watcher {
+    Inotify(Inotify),
+    Poll,
+}
+
+impl Watcher {
+    fn new(follow: &Follow) -> Self {
+        match follow.inotify.then(Inotify::init) {
+            Some(Ok(inotify)) => Watcher::Inotify(inotify),
+            _ => Watcher::Poll,
+        }
+    }
+
+    fn watch(&mut self, name: &str, follow: &Follow) {
+        let Watcher::Inotify(inotify) = self else {
+            return;
+        };
+        let file =
+            WatchMask::MODIFY | WatchMask::ATTRIB | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+        let _ = inotify.watches().add(name, file);
+        // a replacement, or a missing file appearing, shows up as a change to
+        // the directory holding the name
+        if follow.mode == FollowMode::Name || follow.retry {
+            let dir = match Path::new(name).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let entries =
+                WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::DELETE | WatchMask::MOVED_FROM;
+            let _ = inotify.watches().add(dir, entries);
+        }
+    }
+
+    // every file is looked at again after an event, and after the interval
+    // in case an event was missed
+    fn wait(&mut self, interval: Duration) {
+        let Watcher::Inotify(inotify) = self else {
+            thread::sleep(interval);
+            return;
+        };
+        let mut fds = libc::pollfd {
+            fd: inotify.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = interval.as_millis().min(i32::MAX as u128) as i32;
+        // SAFETY: `fds` is a single valid pollfd for the duration of the call
+        unsafe { libc::poll(&mut fds, 1, timeout) };
+        // the events themselves do not matter, only that something happened
+        let mut buffer = [0; 4096];
+        while inotify
+            .read_events(&mut buffer)
+            .is_ok_and(|mut events| events.next().is_some())
+        {}
+    }
+}
@@ -1,15 +1,22 @@
+mod follow;
+
 use crate::TakeValue::*;
 use clap::{value_parser, Arg, ArgAction, Command};
+use follow::{Follow, FollowMode};
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use std::{
+    collections::VecDeque,
     error::Error,
     fs::File,
-    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    time::Duration,
 };
 
 static REGEX: OnceCell<Regex> = OnceCell::new();
 
+const BLOCK_SIZE: usize = 8192;
+
 pub type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,6 +31,8 @@ pub struct Config {
     lines: TakeValue,
     bytes: Option<TakeValue>,
     quiet: bool,
+    zero_terminated: bool,
+    follow: Option<Follow>,
 }
 
 fn parse_num(val: &str) -> Result<TakeValue, String> {
@@ -45,6 +54,14 @@ fn parse_num(val: &str) -> Result<TakeValue, String> {
         _ => Err(val.into()),
     }
 }
+
+fn parse_seconds(val: &str) -> Result<Duration, String> {
+    val.parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid number of seconds: '{}'", val))
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("tailr")
         .author("Ndimah Tchougoua <ndimah22@protonmail.com>")
@@ -85,15 +102,90 @@ pub fn get_args() -> MyResult<Config> {
                 })
                 .default_value("10"),
         )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("follow")
+                .short('f')
+                .long("follow")
+                .value_name("HOW")
+                .help("Output appended data as the file grows, HOW is 'descriptor' (the default) or 'name'")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("descriptor")
+                .value_parser(["descriptor", "name"]),
+        )
+        .arg(
+            Arg::new("follow_name")
+                .short('F')
+                .help("Same as --follow=name, and keep trying to open files that are missing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sleep_interval")
+                .short('s')
+                .long("sleep-interval")
+                .value_name("N")
+                .help("With -f, check the files at least every N seconds")
+                .default_value("1.0")
+                .value_parser(parse_seconds),
+        )
+        .arg(
+            Arg::new("pid")
+                .long("pid")
+                .value_name("PID")
+                .help("With -f, stop following after process PID exits")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("retry")
+                .long("retry")
+                .help("Keep trying to open a file that is missing or becomes inaccessible")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("disable_inotify")
+                .long("disable-inotify")
+                .help("With -f, poll the files instead of waiting for inotify events")
+                .hide(true)
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("files")
                 .value_name("FILES")
                 .num_args(1..)
                 .action(ArgAction::Set)
-                .required(true)
+                .default_value("-")
                 .value_parser(value_parser!(String)),
         )
         .get_matches();
+    let follow_name = matches.get_flag("follow_name");
+    let follow = match matches.get_one::<String>("follow").map(String::as_str) {
+        _ if follow_name => Some(FollowMode::Name),
+        Some("name") => Some(FollowMode::Name),
+        Some(_) => Some(FollowMode::Descriptor),
+        None => None,
+    };
+    let retry = follow_name || matches.get_flag("retry");
+    let pid = matches.get_one::<u32>("pid").copied();
+    match follow {
+        None if retry => {
+            eprintln!("warning: --retry ignored; --retry is useful only when following")
+        }
+        // with a descriptor only the first open can be retried
+        Some(FollowMode::Descriptor) if retry => {
+            eprintln!("warning: --retry only effective for the initial open")
+        }
+        _ => {}
+    }
+    if follow.is_none() && pid.is_some() {
+        eprintln!("warning: PID ignored; --pid=PID is useful only when following");
+    }
     Ok(Config {
         files: matches
             .get_many::<String>("files")
@@ -106,69 +198,232 @@ pub fn get_args() -> MyResult<Config> {
             .clone(),
         bytes: matches.get_one::<TakeValue>("bytes").cloned(),
         quiet: matches.get_flag("quiet"),
+        zero_terminated: matches.get_flag("zero_terminated"),
+        follow: follow.map(|mode| Follow {
+            mode,
+            retry,
+            pid,
+            sleep_interval: *matches
+                .get_one::<Duration>("sleep_interval")
+                .expect("sleep interval should have a default"),
+            inotify: !matches.get_flag("disable_inotify"),
+        }),
     })
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let files = config.files.len();
+    let headers = !config.quiet && config.files.len() > 1;
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    // the regular files stay open so that they can be followed afterwards,
+    // along with their position among the inputs
+    let mut opened = Vec::new();
+    let mut last = None;
     for (index, filename) in config.files.iter().enumerate() {
-        match File::open(&filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                let (lines, bytes) = count_lines_bytes(&filename)?;
-                let reader = BufReader::new(file);
-                if !config.quiet && files > 1 {
-                    println!("{}==> {} <==", if index > 0 { "\n" } else { "" }, filename);
-                }
-                if config.bytes.is_some() {
-                    print_bytes(reader, &config.bytes.clone().unwrap(), bytes)?;
+        let input = match filename.as_str() {
+            "-" => Ok(None),
+            _ => File::open(filename).map(Some),
+        };
+        let file = match input {
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                opened.push((index, filename.clone(), None));
+                continue;
+            }
+            Ok(file) => file,
+        };
+        if headers {
+            let name = if filename == "-" {
+                "standard input"
+            } else {
+                filename
+            };
+            println!("{}==> {} <==", if last.is_some() { "\n" } else { "" }, name);
+            last = Some(index);
+        }
+        match file {
+            Some(file) if file.metadata()?.is_file() => {
+                let mut out = BufWriter::new(io::stdout().lock());
+                if let Some(num_bytes) = &config.bytes {
+                    let bytes = file.metadata()?.len() as i64;
+                    print_bytes(&file, num_bytes, bytes, &mut out)?;
                 } else {
-                    print_lines(reader, &config.lines, lines)?;
+                    print_lines(&file, &config.lines, delimiter, &mut out)?;
                 }
+                out.flush()?;
+                opened.push((index, filename.clone(), Some(file)));
             }
+            // pipes and terminals can only be read once, front to back
+            Some(file) => print_stream(BufReader::new(file), &config, delimiter)?,
+            None => print_stream(io::stdin().lock(), &config, delimiter)?,
         }
     }
+    if let Some(follow) = &config.follow {
+        // there is nothing to follow when only stdin or pipes were given
+        if opened.is_empty() {
+            return Ok(());
+        }
+        return follow::follow(follow, opened, headers, last);
+    }
+    Ok(())
+}
+
+fn print_stream(input: impl BufRead, config: &Config, delimiter: u8) -> MyResult<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match &config.bytes {
+        Some(num_bytes) => print_bytes_stream(input, num_bytes, &mut out)?,
+        None => print_lines_stream(input, &config.lines, delimiter, &mut out)?,
+    }
+    out.flush()?;
     Ok(())
 }
 
-pub fn print_lines(
-    mut file: impl BufRead,
+// tail an input that can only be read once, the last lines are kept in a
+// ring buffer and +N simply skips the first N - 1 lines
+pub fn print_lines_stream(
+    mut input: impl BufRead,
     num_lines: &TakeValue,
-    total_lines: i64,
+    delimiter: u8,
+    out: &mut impl Write,
 ) -> MyResult<()> {
-    if let Some(start_line) = get_start_index(num_lines, total_lines) {
-        let mut buf = String::new();
-        let mut line = 0;
-        loop {
-            let bytes_read = file.read_line(&mut buf)?;
-            if bytes_read == 0 {
-                break;
+    let mut buf = Vec::new();
+    match num_lines {
+        PlusZero => {
+            io::copy(&mut input, out)?;
+        }
+        TakeNum(val) if *val > 0 => {
+            for _ in 1..*val {
+                buf.clear();
+                if input.read_until(delimiter, &mut buf)? == 0 {
+                    return Ok(());
+                }
             }
-            if line >= start_line {
-                print!("{}", buf);
+            io::copy(&mut input, out)?;
+        }
+        TakeNum(val) => {
+            let keep = val.unsigned_abs();
+            let mut ring: VecDeque<Vec<u8>> = VecDeque::new();
+            if keep == 0 {
+                return Ok(());
+            }
+            loop {
+                if input.read_until(delimiter, &mut buf)? == 0 {
+                    break;
+                }
+                ring.push_back(buf);
+                // the line that drops out of the ring lends its buffer to the next one
+                buf = match ring.len() as u64 > keep {
+                    true => ring.pop_front().unwrap_or_default(),
+                    false => Vec::new(),
+                };
+                buf.clear();
             }
-            line += 1;
-            buf.clear();
+            for line in ring {
+                out.write_all(&line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// like `print_lines_stream`, keeping the last bytes instead
+pub fn print_bytes_stream(
+    mut input: impl Read,
+    num_bytes: &TakeValue,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    match num_bytes {
+        PlusZero => {
+            io::copy(&mut input, out)?;
+        }
+        TakeNum(val) if *val > 0 => {
+            io::copy(&mut input.by_ref().take(*val as u64 - 1), &mut io::sink())?;
+            io::copy(&mut input, out)?;
+        }
+        TakeNum(val) => {
+            let keep = val.unsigned_abs();
+            let mut ring: VecDeque<u8> = VecDeque::new();
+            let mut chunk = [0; 8192];
+            loop {
+                let bytes = input.read(&mut chunk)?;
+                if bytes == 0 {
+                    break;
+                }
+                ring.extend(&chunk[..bytes]);
+                if ring.len() as u64 > keep {
+                    ring.drain(..ring.len() - keep as usize);
+                }
+            }
+            let (front, back) = ring.as_slices();
+            out.write_all(front)?;
+            out.write_all(back)?;
+        }
+    }
+    Ok(())
+}
+
+// the last lines of a seekable file are found by reading it backwards from
+// the end, so that the time taken depends on the size of the output only
+pub fn print_lines<T: Read + Seek>(
+    mut file: T,
+    num_lines: &TakeValue,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    match num_lines {
+        TakeNum(val) if *val <= 0 => {
+            let start = find_last_lines(&mut file, val.unsigned_abs(), delimiter)?;
+            file.seek(SeekFrom::Start(start))?;
+            io::copy(&mut file, out)?;
         }
+        _ => print_lines_stream(BufReader::new(file), num_lines, delimiter, out)?,
     }
     Ok(())
 }
 
+// offset where the last `count` lines start, a delimiter ending the file
+// terminates the last line instead of starting an empty one
+pub fn find_last_lines<T: Read + Seek>(file: &mut T, count: u64, delimiter: u8) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if count == 0 {
+        return Ok(len);
+    }
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut pos = len;
+    let mut found = 0;
+    while pos > 0 {
+        let size = pos.min(BLOCK_SIZE as u64) as usize;
+        pos -= size as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block[..size])?;
+        for (i, byte) in block[..size].iter().enumerate().rev() {
+            let end = pos + i as u64 + 1;
+            if *byte == delimiter && end < len {
+                found += 1;
+                if found == count {
+                    return Ok(end);
+                }
+            }
+        }
+    }
+    Ok(0)
+}
+
+// the bytes are copied as they are, a tail may well end in the middle of a
+// multibyte character
 pub fn print_bytes<T: Read + Seek>(
     mut file: T,
     num_bytes: &TakeValue,
     total_bytes: i64,
+    out: &mut impl Write,
 ) -> MyResult<()> {
-    let mut buf = vec![];
-    if let Some(seek) = get_start_index(num_bytes, total_bytes) {
-        file.seek(SeekFrom::Start(seek.try_into().unwrap()))?;
-        loop {
-            let bytes_read = file.read_to_end(&mut buf)?;
-            if bytes_read == 0 {
-                break;
-            }
-            print!("{}", String::from_utf8_lossy(&buf));
-            buf.clear();
+    match get_start_index(num_bytes, total_bytes) {
+        Some(seek) => {
+            file.seek(SeekFrom::Start(seek as u64))?;
+            io::copy(&mut file, out)?;
+        }
+        // nothing to print, but following goes on from the end that was seen
+        None => {
+            file.seek(SeekFrom::Start(total_bytes as u64))?;
         }
     }
     Ok(())
@@ -186,33 +441,17 @@ pub fn get_start_index(take_val: &TakeValue, total: i64) -> Option<i64> {
             if val == &0 || total == 0 || val > &total {
                 None
             } else {
-                let start = if val < &0 {total + val} else {val-1};
-                Some(if start < 0 {0} else {start.try_into().unwrap()})
+                let start = if val < &0 { total + val } else { val - 1 };
+                Some(if start < 0 { 0 } else { start })
             }
         }
     }
 }
 
-pub fn count_lines_bytes(path: &str) -> MyResult<(i64, i64)> {
-    let mut file = BufReader::new(File::open(path)?);
-    let mut lines = 0;
-    let mut bytes = 0;
-    let mut buf = Vec::new();
-    loop {
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
-        if bytes_read == 0 {
-            break;
-        }
-        lines += 1;
-        bytes += bytes_read as i64;
-        buf.clear();
-    }
-    Ok((lines, bytes))
-}
-
 #[cfg(test)]
 mod tests {
-    use super::{count_lines_bytes, get_start_index, parse_num, TakeValue::*};
+    use super::{find_last_lines, get_start_index, parse_num, TakeValue::*};
+    use std::io::Cursor;
     #[test]
     fn test_parse_num() {
         // All integers should be interpreted as negative numbers
@@ -258,12 +497,6 @@ mod tests {
         assert_eq!(res.unwrap_err().to_string(), "foo");
     }
 
-    #[test]
-    fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
-        assert!(res.is_ok_and(|val| val == (1, 24)));
-    }
-
     #[test]
     fn test_get_start_index() {
         // +0 from an empty file (0 lines/bytes) returns None
@@ -291,4 +524,36 @@ mod tests {
         // return 0 to print the whole file
         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(0));
     }
+
+    #[test]
+    fn test_find_last_lines() {
+        let mut text = Cursor::new(b"one\ntwo\nthree\n".to_vec());
+        assert_eq!(find_last_lines(&mut text, 0, b'\n').unwrap(), 14);
+        assert_eq!(find_last_lines(&mut text, 1, b'\n').unwrap(), 8);
+        assert_eq!(find_last_lines(&mut text, 2, b'\n').unwrap(), 4);
+        assert_eq!(find_last_lines(&mut text, 3, b'\n').unwrap(), 0);
+        assert_eq!(find_last_lines(&mut text, 4, b'\n').unwrap(), 0);
+        // the last line does not need a newline
+        let mut text = Cursor::new(b"one\ntwo".to_vec());
+        assert_eq!(find_last_lines(&mut text, 1, b'\n').unwrap(), 4);
+        // empty lines count
+        let mut text = Cursor::new(b"\n\n\n".to_vec());
+        assert_eq!(find_last_lines(&mut text, 2, b'\n').unwrap(), 1);
+        assert_eq!(
+            find_last_lines(&mut Cursor::new(vec![]), 1, b'\n').unwrap(),
+            0
+        );
+        // with NUL delimiters newlines are part of the records
+        let mut text = Cursor::new(b"a\nb\0c\0".to_vec());
+        assert_eq!(find_last_lines(&mut text, 1, b'\0').unwrap(), 4);
+        assert_eq!(find_last_lines(&mut text, 2, b'\0').unwrap(), 0);
+        // lines spread over several blocks
+        let lines: Vec<String> = (0..5000).map(|i| format!("line {i}\n")).collect();
+        let text = lines.concat();
+        let mut cursor = Cursor::new(text.as_bytes().to_vec());
+        for count in [1, 100, 1500, 4999] {
+            let start = find_last_lines(&mut cursor, count, b'\n').unwrap() as usize;
+            assert_eq!(text[start..], lines[5000 - count as usize..].concat());
+        }
+    }
 }
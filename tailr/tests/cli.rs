@@ -1,8 +1,12 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs::{self, File};
-use std::io::Read;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use tailr::MyResult as Result;
 
 const PRG: &str = "tailr";
@@ -11,6 +15,7 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TWELVE: &str = "tests/inputs/twelve.txt";
+const FILES0: &str = "tests/inputs/files0.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -33,13 +38,8 @@ fn gen_bad_file() -> String {
 
 // --------------------------------------------------
 #[test]
-fn dies_no_args() -> Result<()> {
-    Command::cargo_bin(PRG)?
-        .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
-
-    Ok(())
+fn reads_stdin_without_args() -> Result<()> {
+    run_stdin(&[], TWELVE, "tests/expected/twelve.txt.out")
 }
 
 // --------------------------------------------------
@@ -100,15 +100,27 @@ fn skips_bad_file() -> Result<()> {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    let expected = fs::read(expected_file)?;
 
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
     assert!(output.status.success());
-    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
+    let input = fs::read(input_file)?;
+
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(args)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
 
     Ok(())
 }
@@ -827,3 +839,494 @@ fn multiple_files_c_plus_3() -> Result<()> {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+// runs tailr in the background, collecting what it prints until it is dropped
+struct Follower {
+    child: Child,
+    stdout: Arc<Mutex<Vec<u8>>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Follower {
+    fn spawn(args: &[&str]) -> Result<Self> {
+        Self::spawn_with_stdin(args, Stdio::null())
+    }
+
+    fn spawn_with_stdin(args: &[&str], stdin: Stdio) -> Result<Self> {
+        let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(args)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = Arc::new(Mutex::new(vec![]));
+        let stderr = Arc::new(Mutex::new(vec![]));
+        let mut out = child.stdout.take().unwrap();
+        let mut err = child.stderr.take().unwrap();
+        let (collected_out, collected_err) = (stdout.clone(), stderr.clone());
+        thread::spawn(move || collect(&mut out, &collected_out));
+        thread::spawn(move || collect(&mut err, &collected_err));
+        Ok(Follower {
+            child,
+            stdout,
+            stderr,
+        })
+    }
+
+    // wait until the output is exactly `expected`, or give up after a while
+    fn expect_stdout(&self, expected: &str) {
+        wait_for(&self.stdout, expected, |output| output == expected);
+    }
+
+    fn expect_stderr(&self, expected: &str) {
+        wait_for(&self.stderr, expected, |output| output.contains(expected));
+    }
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn collect(input: &mut impl Read, output: &Mutex<Vec<u8>>) {
+    let mut buffer = [0; 1024];
+    while let Ok(bytes @ 1..) = input.read(&mut buffer) {
+        output.lock().unwrap().extend_from_slice(&buffer[..bytes]);
+    }
+}
+
+fn wait_for(output: &Mutex<Vec<u8>>, expected: &str, done: impl Fn(&str) -> bool) {
+    let start = Instant::now();
+    loop {
+        let output = String::from_utf8_lossy(&output.lock().unwrap()).to_string();
+        if done(&output) {
+            return;
+        }
+        if start.elapsed() > Duration::from_secs(5) {
+            assert_eq!(output, expected);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn append(path: &std::path::Path, text: &str) -> Result<()> {
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(text.as_bytes())?;
+    Ok(())
+}
+
+#[test]
+fn follow_descriptor() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\ntwo\nthree\n")?;
+    let tailr = Follower::spawn(&["-n", "2", "-f", log.to_str().unwrap()])?;
+    tailr.expect_stdout("two\nthree\n");
+    append(&log, "four\n")?;
+    tailr.expect_stdout("two\nthree\nfour\n");
+    // the renamed file is still the one being followed
+    let renamed = dir.path().join("log.1");
+    fs::rename(&log, &renamed)?;
+    append(&renamed, "five\n")?;
+    tailr.expect_stdout("two\nthree\nfour\nfive\n");
+    Ok(())
+}
+
+#[test]
+fn follow_keeps_what_was_appended_while_reading_stdin() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\n")?;
+    let name = log.to_str().unwrap();
+    let mut tailr = Follower::spawn_with_stdin(&["-f", name, "-"], Stdio::piped())?;
+    let mut expected = format!("==> {name} <==\none\n\n==> standard input <==\n");
+    tailr.expect_stdout(&expected);
+    // the log grows before following starts, while stdin is still open
+    append(&log, "two\n")?;
+    drop(tailr.child.stdin.take());
+    expected.push_str(&format!("\n==> {name} <==\ntwo\n"));
+    tailr.expect_stdout(&expected);
+    Ok(())
+}
+
+#[test]
+fn follow_c0_starts_at_the_end() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\n")?;
+    let tailr = Follower::spawn(&["-c", "0", "-f", log.to_str().unwrap()])?;
+    // wait for the tail to be done before appending
+    thread::sleep(Duration::from_millis(300));
+    append(&log, "two\n")?;
+    tailr.expect_stdout("two\n");
+    Ok(())
+}
+
+#[test]
+fn follow_polling() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\n")?;
+    let tailr = Follower::spawn(&[
+        "--follow",
+        "--disable-inotify",
+        "-s",
+        "0.05",
+        log.to_str().unwrap(),
+    ])?;
+    tailr.expect_stdout("one\n");
+    append(&log, "two\n")?;
+    tailr.expect_stdout("one\ntwo\n");
+    Ok(())
+}
+
+#[test]
+fn follow_truncated() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\ntwo\n")?;
+    let tailr = Follower::spawn(&["-f", log.to_str().unwrap()])?;
+    tailr.expect_stdout("one\ntwo\n");
+    fs::write(&log, "new\n")?;
+    tailr.expect_stdout("one\ntwo\nnew\n");
+    tailr.expect_stderr("file truncated");
+    Ok(())
+}
+
+#[test]
+fn follow_name_rotation() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\n")?;
+    let tailr = Follower::spawn(&["-F", "-s", "0.1", log.to_str().unwrap()])?;
+    tailr.expect_stdout("one\n");
+    fs::rename(&log, dir.path().join("log.1"))?;
+    fs::write(&log, "rotated\n")?;
+    tailr.expect_stdout("one\nrotated\n");
+    append(&log, "more\n")?;
+    tailr.expect_stdout("one\nrotated\nmore\n");
+    Ok(())
+}
+
+#[test]
+fn follow_headers_switch_between_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+    fs::write(&first, "1\n")?;
+    fs::write(&second, "2\n")?;
+    let (first_name, second_name) = (first.to_str().unwrap(), second.to_str().unwrap());
+    let tailr = Follower::spawn(&["-f", first_name, second_name])?;
+    let mut expected = format!("==> {first_name} <==\n1\n\n==> {second_name} <==\n2\n");
+    tailr.expect_stdout(&expected);
+    append(&second, "22\n")?;
+    expected.push_str("22\n");
+    tailr.expect_stdout(&expected);
+    append(&first, "11\n")?;
+    expected.push_str(&format!("\n==> {first_name} <==\n11\n"));
+    tailr.expect_stdout(&expected);
+    Ok(())
+}
+
+#[test]
+fn follow_after_stdin_keeps_headers() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "1\n")?;
+    let name = log.to_str().unwrap();
+    // stdin is shown but not followed, the log is still the second input
+    let tailr = Follower::spawn(&["-f", "-", name])?;
+    let mut expected = format!("==> standard input <==\n\n==> {name} <==\n1\n");
+    tailr.expect_stdout(&expected);
+    append(&log, "2\n")?;
+    expected.push_str("2\n");
+    tailr.expect_stdout(&expected);
+    Ok(())
+}
+
+#[test]
+fn follow_quiet_has_no_headers() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+    fs::write(&first, "1\n")?;
+    fs::write(&second, "2\n")?;
+    let tailr = Follower::spawn(&[
+        "-q",
+        "-f",
+        first.to_str().unwrap(),
+        second.to_str().unwrap(),
+    ])?;
+    tailr.expect_stdout("1\n2\n");
+    append(&first, "11\n")?;
+    tailr.expect_stdout("1\n2\n11\n");
+    Ok(())
+}
+
+#[test]
+fn follow_no_files_remaining() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no files remaining"));
+    Ok(())
+}
+
+#[test]
+fn follow_retry_until_file_appears() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    let tailr = Follower::spawn(&["-f", "--retry", "-s", "0.1", log.to_str().unwrap()])?;
+    tailr.expect_stderr("No such file or directory");
+    tailr.expect_stderr("--retry only effective for the initial open");
+    fs::write(&log, "one\n")?;
+    tailr.expect_stdout("one\n");
+    tailr.expect_stderr("has appeared; following new file");
+    append(&log, "two\n")?;
+    tailr.expect_stdout("one\ntwo\n");
+    Ok(())
+}
+
+#[test]
+fn follow_name_retry_inaccessible() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    fs::write(&log, "one\n")?;
+    let name = log.to_str().unwrap();
+    let tailr = Follower::spawn(&["--follow=name", "--retry", "-s", "0.1", name])?;
+    tailr.expect_stdout("one\n");
+    fs::remove_file(&log)?;
+    tailr.expect_stderr(&format!("'{name}' has become inaccessible"));
+    fs::write(&log, "back\n")?;
+    tailr.expect_stderr(&format!("'{name}' has appeared; following new file"));
+    tailr.expect_stdout("one\nback\n");
+    Ok(())
+}
+
+#[test]
+fn follow_stops_when_pid_exits() -> Result<()> {
+    let mut sleep = std::process::Command::new("sleep").arg("0.5").spawn()?;
+    // the sleep is not reaped until the end, so it lingers as a zombie
+    Command::cargo_bin(PRG)?
+        .args(["-f", "--pid", &sleep.id().to_string(), ONE])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout(fs::read("tests/expected/one.txt.out")?);
+    sleep.wait()?;
+    Ok(())
+}
+
+#[test]
+fn retry_and_pid_ignored_without_follow() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--retry", "--pid", "1", EMPTY])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "--retry is useful only when following",
+        ))
+        .stderr(predicate::str::contains(
+            "--pid=PID is useful only when following",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_sleep_interval() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", "soon", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of seconds: 'soon'",
+        ));
+    Ok(())
+}
+
+#[test]
+fn files0_z_n1() -> Result<()> {
+    run(
+        &["-z", "-n", "1", FILES0],
+        "tests/expected/files0.txt.z.n1.out",
+    )
+}
+
+#[test]
+fn files0_z_n2() -> Result<()> {
+    run(
+        &["--zero-terminated", "-n", "2", FILES0],
+        "tests/expected/files0.txt.z.n2.out",
+    )
+}
+
+#[test]
+fn files0_z_n10() -> Result<()> {
+    run(
+        &["-z", "-n", "10", FILES0],
+        "tests/expected/files0.txt.z.n10.out",
+    )
+}
+
+#[test]
+fn files0_z_n0() -> Result<()> {
+    run(
+        &["-z", "-n", "0", FILES0],
+        "tests/expected/files0.txt.z.n0.out",
+    )
+}
+
+#[test]
+fn files0_z_n_plus0() -> Result<()> {
+    run(
+        &["-z", "-n", "+0", FILES0],
+        "tests/expected/files0.txt.z.n+0.out",
+    )
+}
+
+#[test]
+fn files0_z_n_plus2() -> Result<()> {
+    run(
+        &["-z", "-n", "+2", FILES0],
+        "tests/expected/files0.txt.z.n+2.out",
+    )
+}
+
+#[test]
+fn files0_n2() -> Result<()> {
+    run(&["-n", "2", FILES0], "tests/expected/files0.txt.n2.out")
+}
+
+#[test]
+fn stdin_files0_z_n2() -> Result<()> {
+    run_stdin(
+        &["-z", "-n", "2"],
+        FILES0,
+        "tests/expected/files0.txt.z.n2.out",
+    )
+}
+
+#[test]
+fn stdin_files0_z_n_plus2() -> Result<()> {
+    run_stdin(
+        &["-z", "-n", "+2"],
+        FILES0,
+        "tests/expected/files0.txt.z.n+2.out",
+    )
+}
+
+#[test]
+fn stdin_n3() -> Result<()> {
+    run_stdin(
+        &["-n", "3", "-"],
+        TWELVE,
+        "tests/expected/twelve.txt.n3.out",
+    )
+}
+
+#[test]
+fn stdin_n_plus_2() -> Result<()> {
+    run_stdin(&["-n", "+2"], TWELVE, "tests/expected/twelve.txt.n+2.out")
+}
+
+#[test]
+fn stdin_n200() -> Result<()> {
+    run_stdin(&["-n", "200"], THREE, "tests/expected/three.txt.n200.out")
+}
+
+#[test]
+fn stdin_n0() -> Result<()> {
+    run_stdin(&["-n", "0"], TWELVE, "tests/expected/twelve.txt.n0.out")
+}
+
+#[test]
+fn stdin_c8() -> Result<()> {
+    run_stdin(&["-c", "8"], TWELVE, "tests/expected/twelve.txt.c8.out")
+}
+
+#[test]
+fn stdin_c_plus_2() -> Result<()> {
+    run_stdin(&["-c", "+2"], ONE, "tests/expected/one.txt.c+2.out")
+}
+
+#[test]
+fn stdin_c_plus_0() -> Result<()> {
+    run_stdin(&["-c", "+0"], THREE, "tests/expected/three.txt.c+0.out")
+}
+
+#[test]
+fn stdin_among_files() -> Result<()> {
+    let expected = format!(
+        "==> {ONE} <==\n{}\n==> standard input <==\n{}",
+        fs::read_to_string("tests/expected/one.txt.n1.out")?,
+        fs::read_to_string("tests/expected/twelve.txt.n1.out")?,
+    );
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", ONE, "-"])
+        .write_stdin(fs::read(TWELVE)?)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn large_stdin_n_and_c() -> Result<()> {
+    let input: String = (1..=100_000).map(|i| format!("{i}\n")).collect();
+    let expected: String = (99_991..=100_000).map(|i| format!("{i}\n")).collect();
+    Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(expected);
+    Command::cargo_bin(PRG)?
+        .args(["-c", "20000"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[input.len() - 20_000..].to_string());
+    Ok(())
+}
+
+#[test]
+fn fifo_n3() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("fifo");
+    let status = std::process::Command::new("mkfifo").arg(&fifo).status()?;
+    assert!(status.success());
+    let writer = {
+        let fifo = fifo.clone();
+        thread::spawn(move || fs::write(fifo, fs::read(TWELVE).unwrap()))
+    };
+    let output = Command::cargo_bin(PRG)?
+        .args(["-n", "3", fifo.to_str().unwrap()])
+        .timeout(Duration::from_secs(5))
+        .output()?;
+    writer.join().unwrap()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/expected/twelve.txt.n3.out")?);
+    Ok(())
+}
+
+#[test]
+fn large_sparse_file_n2() -> Result<()> {
+    // the lines are at the end of a large hole, reading the whole file first
+    // would take far longer than the timeout
+    let file = tempfile::NamedTempFile::new()?;
+    file.as_file().set_len(8 << 30)?;
+    append(file.path(), "a\nb\nc\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", file.path().to_str().unwrap()])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout("b\nc\n");
+    Ok(())
+}